@@ -1,10 +1,21 @@
 use clap::{Parser, Subcommand};
 
+use crate::{commands::split::SplitMode, git_provider::SupportedProviders};
+
 /// Git Improved
 #[derive(Parser, Debug)]
 #[command(version)]
 #[command(propagate_version = true)]
 pub struct Args {
+    /// Never prompt, failing instead when a value is missing. Implied when stdin isn't a terminal
+    #[arg(long, global = true)]
+    pub no_interactive: bool,
+    /// Print the result of the command, or its error, as a JSON object
+    #[arg(long, global = true)]
+    pub json: bool,
+    /// The trunk branch of the repository, remembered for the next commands
+    #[arg(long, global = true)]
+    pub trunk: Option<String>,
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -12,7 +23,112 @@ pub struct Args {
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     #[clap(aliases = &["c"])]
-    Create,
+    Create {
+        /// Stage every change of the working area before committing
+        #[arg(short, long)]
+        all: bool,
+        /// The commit message, instead of writing one in the editor
+        #[arg(short, long)]
+        message: Option<String>,
+    },
     #[clap(aliases = &["s"])]
-    Submit,
+    Submit {
+        /// Submit every branch from the bottom of the stack up to the current one
+        #[arg(long)]
+        stack: bool,
+        /// The title of the pull request of the current branch
+        #[arg(long)]
+        title: Option<String>,
+        /// The description of the pull request of the current branch
+        #[arg(long, conflicts_with = "body_file")]
+        body: Option<String>,
+        /// Read the description of the pull request of the current branch from a file, or stdin with `-`
+        #[arg(long)]
+        body_file: Option<String>,
+    },
+    #[clap(aliases = &["l"])]
+    Log,
+    #[clap(aliases = &["r"])]
+    Restack,
+    Continue,
+    Abort,
+    #[clap(aliases = &["m"])]
+    Modify {
+        /// Add a new commit to the current branch instead of amending its last one
+        #[arg(short, long)]
+        commit: bool,
+        /// Stage every change of the working area before committing
+        #[arg(short, long)]
+        all: bool,
+        /// The commit message, instead of keeping the current one or writing one in the editor
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+    #[clap(aliases = &["u"])]
+    Up {
+        /// How many branches to move up the stack
+        #[arg(default_value_t = 1)]
+        steps: usize,
+    },
+    #[clap(aliases = &["d"])]
+    Down {
+        /// How many branches to move down the stack
+        #[arg(default_value_t = 1)]
+        steps: usize,
+    },
+    Top,
+    Bottom,
+    /// Stack the current branch on another parent, rebasing its commits onto it
+    Move {
+        /// The branch to stack the current one on
+        #[arg(long)]
+        onto: String,
+        /// Move the branches stacked on top of the current one along with it
+        #[arg(short, long)]
+        descendants: bool,
+    },
+    /// Split the current branch into a stack of branches, by commit or by hunk
+    Split {
+        /// How to split the branch, instead of picking it in a prompt
+        #[arg(long, value_enum)]
+        by: Option<SplitMode>,
+    },
+    /// Merge the current branch into its parent, which takes over its children
+    Fold,
+    /// Collapse the commits of the current branch into one, then restack the branches above
+    Squash {
+        /// The message of the squashed commit, instead of writing one in the editor
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+    /// Start tracking a branch made outside of gi, stacking it on top of a parent
+    Track {
+        /// The branch to track, the current one by default
+        branch: Option<String>,
+        /// The branch it is stacked on, instead of picking among the most likely ones
+        #[arg(short, long)]
+        parent: Option<String>,
+    },
+    /// Stop tracking a branch, leaving the branch itself untouched
+    Untrack {
+        /// The branch to stop tracking, the current one by default
+        branch: Option<String>,
+    },
+    /// Pull the trunk, delete the branches whose pull requests were merged or closed, and restack
+    Sync {
+        /// Delete the branches of merged and closed pull requests without asking
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Configure which provider serves a git host that gi doesn't recognise by its name
+    Host {
+        /// The host of the remote, e.g. `git.corp.example`
+        host: String,
+        /// The provider serving the host
+        #[arg(long, value_enum)]
+        provider: SupportedProviders,
+        /// The base URL of the provider API, when it isn't the default one of the host
+        #[arg(long)]
+        api_url: Option<String>,
+    },
 }
@@ -0,0 +1,38 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use color_eyre::Section;
+use eyre::{eyre, Result};
+
+/// Whether gi may prompt the user, which is only the case when it's asked from a terminal.
+static INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Allows or forbids prompting the user for the rest of the process.
+pub fn set_interactive(interactive: bool) {
+    INTERACTIVE.store(interactive, Ordering::Relaxed);
+}
+
+/// Returns whether gi may prompt the user.
+pub fn is_interactive() -> bool {
+    INTERACTIVE.load(Ordering::Relaxed)
+}
+
+/// Makes sure the user can be prompted for a value gi can't do without.
+///
+/// # Arguments
+///
+/// * `value` - What gi would ask the user for, e.g. "the name of the trunk branch".
+/// * `suggestion` - How to provide the value without a prompt.
+///
+/// # Returns
+///
+/// A `Result` that is an error explaining how to provide the value when gi runs non-interactively.
+pub fn ensure_interactive(value: &str, suggestion: &str) -> Result<()> {
+    if is_interactive() {
+        return Ok(());
+    }
+
+    Err(eyre!(
+        "gi needs {value}, but can't prompt for it when running non-interactively."
+    ))
+    .suggestion(suggestion.to_owned())
+}
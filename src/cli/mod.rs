@@ -1 +1,3 @@
 pub mod args;
+pub mod interactive;
+pub mod output;
@@ -0,0 +1,165 @@
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use color_eyre::config::{HookBuilder, Theme};
+use eyre::Result;
+use serde_json::{json, Value};
+
+/// Whether commands print their result as a JSON object instead of text.
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Switches the output of the rest of the process to JSON.
+pub fn set_json(json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// The labels `color_eyre` starts the help sections of a report with.
+const SECTION_LABELS: [&str; 3] = ["Note: ", "Warning: ", "Suggestion: "];
+
+/// Installs the hooks rendering errors and panics, once the output is known to be text or JSON.
+///
+/// Reports are rendered without colors nor hints about the environment in JSON mode, so that
+/// `emit_error` can read the suggestions back from them.
+pub fn install_error_hooks() -> Result<()> {
+    if is_json() {
+        json_hooks().install()
+    } else {
+        color_eyre::install()
+    }
+}
+
+/// The hooks of the JSON output, rendering reports without colors nor the environment section.
+fn json_hooks() -> HookBuilder {
+    HookBuilder::default()
+        .theme(Theme::new())
+        .display_env_section(false)
+}
+
+/// Prints a message meant for people, which is left out of the JSON output.
+pub fn print(message: impl Display) {
+    if !is_json() {
+        println!("{message}");
+    }
+}
+
+/// Prints the result of a command as a single JSON object, when the output is JSON.
+///
+/// # Arguments
+///
+/// * `result` - The structured result of the command.
+pub fn emit(result: Value) {
+    if is_json() {
+        println!("{result}");
+    }
+}
+
+/// Prints an error as a JSON object holding its message, its causes and the suggestions attached to it.
+///
+/// # Arguments
+///
+/// * `error` - The error a command failed with.
+pub fn emit_error(error: &eyre::Report) {
+    let causes = error
+        .chain()
+        .skip(1)
+        .map(|cause| cause.to_string())
+        .collect::<Vec<_>>();
+
+    println!(
+        "{}",
+        json!({
+            "error": {
+                "message": error.to_string(),
+                "causes": causes,
+                "suggestion": suggestion(error),
+            }
+        })
+    );
+}
+
+/// Extracts the suggestions attached to an error with `color_eyre::Section::suggestion`.
+///
+/// `color_eyre` only exposes its sections through the report it renders, so they're read back from it.
+/// The help sections come last and are the only lines of the report that aren't indented, so a
+/// suggestion spans every line up to the next section.
+fn suggestion(error: &eyre::Report) -> Option<String> {
+    let report = format!("{error:?}");
+
+    let mut suggestions = Vec::new();
+    let mut current: Option<Vec<&str>> = None;
+    for line in report.lines() {
+        if SECTION_LABELS.iter().any(|label| line.starts_with(label)) {
+            suggestions.extend(current.take());
+            current = line.strip_prefix("Suggestion: ").map(|first| vec![first]);
+        } else if let Some(lines) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+    suggestions.extend(current);
+
+    let suggestions = suggestions
+        .into_iter()
+        .map(|lines| lines.join("\n").trim_end().to_string())
+        .collect::<Vec<_>>();
+
+    (!suggestions.is_empty()).then(|| suggestions.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Once;
+
+    use color_eyre::Section;
+
+    use super::*;
+
+    /// Installs the hooks of the JSON output, which only the first call in the process can do.
+    fn install_json_hooks() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| json_hooks().install().unwrap());
+    }
+
+    #[test]
+    fn suggestions_are_read_back_from_the_report() {
+        install_json_hooks();
+        let error = eyre::eyre!("Branch lexer doesn't exist.")
+            .suggestion("Run `git branch` to list the branches of the repository.");
+
+        assert_eq!(
+            suggestion(&error).as_deref(),
+            Some("Run `git branch` to list the branches of the repository.")
+        );
+    }
+
+    #[test]
+    fn multi_line_suggestions_are_kept_whole() {
+        install_json_hooks();
+        let error = eyre::eyre!("Failed to push")
+            .wrap_err("Failed to submit the stack")
+            .suggestion("Pull the remote branch:\n\n  git pull --rebase\n\nthen submit again.")
+            .note("The remote branch has commits that aren't in the local one.")
+            .suggestion("Or force push with `git push --force`.");
+
+        assert_eq!(
+            suggestion(&error).as_deref(),
+            Some(
+                "Pull the remote branch:\n\n  git pull --rebase\n\nthen submit again.\n\
+                 Or force push with `git push --force`."
+            )
+        );
+    }
+
+    #[test]
+    fn errors_without_suggestions_have_none() {
+        install_json_hooks();
+        let error = eyre::eyre!("Failed to push").note("The remote rejected the push.");
+
+        assert_eq!(suggestion(&error), None);
+    }
+}
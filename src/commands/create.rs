@@ -4,15 +4,38 @@ use color_eyre::Section;
 use dialoguer::theme::ColorfulTheme;
 use eyre::{Context, Ok, Result};
 use regex::Regex;
+use serde_json::json;
 
-use crate::git_client::{
-    get_git_client,
-    metadata::{create_branch_metadata, BranchMetadata},
+use crate::{
+    cli::{interactive::is_interactive, output},
+    git_client::{
+        metadata::{create_branch_metadata, BranchMetadata},
+        CommitStatus, GitClient, WorkingArea,
+    },
 };
 
-pub fn create() -> Result<()> {
-    let git_client = get_git_client()?;
+/// How the changes of the working area are committed.
+pub struct CommitOptions {
+    /// Whether to stage every change of the working area before committing.
+    pub all: bool,
+    /// The commit message, instead of writing one in the editor.
+    pub message: Option<String>,
+    /// Whether to ask the user what to stage and to open the editor for the commit message.
+    pub interactive: bool,
+}
+
+impl CommitOptions {
+    /// Creates commit options, which only allow prompting when gi runs interactively.
+    pub fn new(all: bool, message: Option<String>) -> Self {
+        Self {
+            all,
+            message,
+            interactive: is_interactive(),
+        }
+    }
+}
 
+pub fn create(git_client: &dyn GitClient, options: &CommitOptions) -> Result<()> {
     let Some(base_branch) = git_client.get_current_branch() else {
         return Err(
             eyre::eyre!("Can't create a new stacked commit without a current branch.")
@@ -28,37 +51,22 @@ pub fn create() -> Result<()> {
         .suggestion("Make some changes to your files and try again."));
     }
 
-    if !working_area.has_staged_changes() {
-        let items = vec!["Commit all changes (--all)", "Abort operation"];
-
-        let selection = dialoguer::Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("You have no staged changes. What would you like to do?")
-            .items(&items)
-            .default(0)
-            .interact()?;
-
-        match items[selection] {
-            "Commit all changes (--all)" => {
-                git_client.add_all()?;
-            }
-            "Abort operation" => {
-                return Ok(());
-            }
-            _ => unreachable!(),
-        }
+    if !stage_changes(git_client, &working_area, options)? {
+        return Ok(());
     }
 
     let temp_branch = git_client.create_branch("gi_temp_branch")?;
     git_client.checkout(&temp_branch)?;
 
-    let commit_status = git_client.interactive_commit()?;
-    match commit_status {
-        crate::git_client::CommitStatus::Aborted => {
-            git_client.checkout(&base_branch)?;
-            git_client.delete_branch(&temp_branch)?;
-            return Ok(());
-        }
-        crate::git_client::CommitStatus::Success => {}
+    // Whatever happens to the commit, the temporary branch mustn't outlive it
+    let commit_status = commit(git_client, options);
+    if !matches!(commit_status.as_ref().ok(), Some(CommitStatus::Success)) {
+        git_client.checkout(&base_branch)?;
+        git_client.delete_branch(&temp_branch)?;
+        commit_status?;
+
+        output::emit(json!({ "branch": null, "aborted": true }));
+        return Ok(());
     }
 
     let commit_title = git_client.get_current_commit_title()?;
@@ -68,15 +76,99 @@ pub fn create() -> Result<()> {
     git_client.delete_branch(&temp_branch)?;
 
     let base_branch_revision = git_client.get_branch_revision(&base_branch)?;
-    create_branch_metadata(
-        &git_client,
-        new_branch,
-        &BranchMetadata::new(base_branch, base_branch_revision),
-    )?;
+    let metadata = BranchMetadata::new(base_branch, base_branch_revision);
+    create_branch_metadata(git_client, new_branch.clone(), &metadata)?;
+
+    output::emit(json!({
+        "branch": new_branch,
+        "revision": git_client.get_branch_revision(&new_branch)?,
+        "metadata": metadata,
+    }));
 
     Ok(())
 }
 
+/// Commits the staged changes, with the message of the options if there is one and in the editor otherwise.
+///
+/// # Arguments
+///
+/// * `git_client` - The git client used to commit the changes.
+/// * `options` - How the changes are committed.
+///
+/// # Returns
+///
+/// A `Result` containing the commit status, or an error if there is no message and no editor can be opened.
+pub fn commit(git_client: &dyn GitClient, options: &CommitOptions) -> Result<CommitStatus> {
+    if let Some(message) = &options.message {
+        git_client.commit_with_message(message)?;
+
+        return Ok(CommitStatus::Success);
+    }
+
+    if !options.interactive {
+        return Err(eyre::eyre!(
+            "Can't open the editor for the commit message when running non-interactively."
+        )
+        .suggestion("Pass the commit message with `-m`, e.g. `-m \"Add the parser\"`."));
+    }
+
+    git_client.interactive_commit()
+}
+
+/// Makes sure there are staged changes to commit, offering to stage every change when there are none.
+///
+/// # Arguments
+///
+/// * `git_client` - The git client used to stage the changes.
+/// * `working_area` - The current state of the working area.
+/// * `options` - Whether to stage every change without asking, and whether asking is possible.
+///
+/// # Returns
+///
+/// A `Result` containing `false` if the user chose to abort the operation.
+pub fn stage_changes(
+    git_client: &dyn GitClient,
+    working_area: &WorkingArea,
+    options: &CommitOptions,
+) -> Result<bool> {
+    if options.all {
+        git_client.add_all()?;
+        return Ok(true);
+    }
+
+    if working_area.has_staged_changes() {
+        return Ok(true);
+    }
+
+    if !options.interactive {
+        return Err(
+            eyre::eyre!("There are no staged changes to commit.").suggestion(
+                "Stage the changes with `git add`, or pass `--all` to commit all of them.",
+            ),
+        );
+    }
+
+    let items = vec!["Commit all changes (--all)", "Abort operation"];
+
+    let selection = dialoguer::Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("You have no staged changes. What would you like to do?")
+        .items(&items)
+        .default(0)
+        .interact()?;
+
+    match items[selection] {
+        "Commit all changes (--all)" => {
+            git_client.add_all()?;
+        }
+        "Abort operation" => {
+            return Ok(false);
+        }
+        _ => unreachable!(),
+    }
+
+    Ok(true)
+}
+
 /// Formats the commit title by replacing non-alphanumeric characters with underscores and prepending the current date.
 ///
 /// # Arguments
@@ -90,7 +182,7 @@ pub fn create() -> Result<()> {
 /// # Examples
 ///
 /// `format_commit_title("Hello, World")` returns `Ok("12-31_Hello_World")`
-fn format_commit_title(title: String) -> Result<String> {
+pub(crate) fn format_commit_title(title: String) -> Result<String> {
     // Get day and month from the current date
     let today = chrono::Local::now().format("%m-%d").to_string();
 
@@ -100,3 +192,109 @@ fn format_commit_title(title: String) -> Result<String> {
     // Format the commit title
     Ok(format!("{}-{}", today, normalized_title))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{git_client::metadata::read_branch_metadata, testing::FakeGitClient};
+
+    /// Options committing the staged changes in the editor.
+    fn interactive() -> CommitOptions {
+        CommitOptions {
+            all: false,
+            message: None,
+            interactive: true,
+        }
+    }
+
+    /// Options committing without any prompt.
+    fn non_interactive(all: bool, message: Option<&str>) -> CommitOptions {
+        CommitOptions {
+            all,
+            message: message.map(str::to_string),
+            interactive: false,
+        }
+    }
+
+    #[test]
+    fn create_commits_onto_a_new_stacked_branch() {
+        let git_client = FakeGitClient::new("main");
+        git_client.stage_files(&["src/lib.rs"]);
+        git_client.queue_commit_message("Add the parser", "");
+
+        create(&git_client, &interactive()).unwrap();
+
+        let branch = git_client.get_current_branch().unwrap();
+        assert!(branch.ends_with("-Add_the_parser"), "{branch}");
+        assert_eq!(git_client.branches(), vec![branch.clone(), "main".into()]);
+        assert_eq!(
+            git_client.log(&branch),
+            vec!["Add the parser", "Initial commit"]
+        );
+
+        let metadata = read_branch_metadata(&git_client, &branch).unwrap().unwrap();
+        assert_eq!(metadata.parent_branch_name(), "main");
+        assert_eq!(
+            metadata.parent_branch_revision(),
+            git_client.get_branch_revision("main").unwrap()
+        );
+    }
+
+    #[test]
+    fn create_cleans_up_when_the_commit_is_aborted() {
+        let git_client = FakeGitClient::new("main");
+        git_client.stage_files(&["src/lib.rs"]);
+
+        create(&git_client, &interactive()).unwrap();
+
+        assert_eq!(git_client.get_current_branch().as_deref(), Some("main"));
+        assert_eq!(git_client.branches(), vec!["main"]);
+    }
+
+    #[test]
+    fn create_commits_every_change_with_the_message_when_non_interactive() {
+        let git_client = FakeGitClient::new("main");
+        git_client.modify_files(&["src/lib.rs"]);
+
+        create(
+            &git_client,
+            &non_interactive(true, Some("Add the parser\n\nIt parses.")),
+        )
+        .unwrap();
+
+        let branch = git_client.get_current_branch().unwrap();
+        assert!(branch.ends_with("-Add_the_parser"), "{branch}");
+        assert_eq!(git_client.get_commit_body(&branch).unwrap(), "It parses.");
+    }
+
+    #[test]
+    fn create_fails_instead_of_prompting_when_non_interactive() {
+        let git_client = FakeGitClient::new("main");
+        git_client.modify_files(&["src/lib.rs"]);
+
+        let error = create(&git_client, &non_interactive(false, Some("Add the parser")));
+        assert!(error.is_err());
+
+        // Without a message, the temporary branch is cleaned up once the commit fails
+        git_client.stage_files(&["src/lib.rs"]);
+        let error = create(&git_client, &non_interactive(false, None)).unwrap_err();
+        assert!(error.to_string().contains("non-interactively"), "{error}");
+
+        assert_eq!(git_client.get_current_branch().as_deref(), Some("main"));
+        assert_eq!(git_client.branches(), vec!["main"]);
+    }
+
+    #[test]
+    fn create_requires_changes() {
+        let git_client = FakeGitClient::new("main");
+
+        assert!(create(&git_client, &interactive()).is_err());
+    }
+
+    #[test]
+    fn format_commit_title_replaces_punctuation() {
+        let title = format_commit_title("Hello, World!".to_string()).unwrap();
+
+        assert!(title.ends_with("-Hello_World_"), "{title}");
+    }
+}
@@ -0,0 +1,158 @@
+use color_eyre::Section;
+use eyre::{Ok, OptionExt, Result};
+use serde_json::json;
+
+use crate::{
+    cli::output,
+    commands::StackOptions,
+    git_client::{metadata::delete_branch_metadata, GitClient},
+    stack::{operation::Operation, reparent_children, StackGraph},
+};
+
+/// Merges the current branch into its parent, which takes over its commits and its children.
+///
+/// The current branch and its metadata are deleted and the parent is checked out. The other
+/// branches stacked on the parent are restacked onto its new revision.
+///
+/// # Arguments
+///
+/// * `git_client` - The git client of the repository.
+/// * `options` - The trunk of the repository, which branches can't be folded into.
+pub fn fold(git_client: &dyn GitClient, options: &StackOptions) -> Result<()> {
+    Operation::ensure_none_in_progress(git_client)?;
+
+    let branch = git_client
+        .get_current_branch()
+        .ok_or_eyre("Failed to get the current branch")
+        .suggestion("Check out the branch to fold.")?;
+    let graph = StackGraph::load(git_client, options.trunk.clone())?;
+    let Some(parent) = graph.parent(&branch).map(str::to_string) else {
+        return Err(eyre::eyre!("Branch {} is not tracked.", branch)).suggestion(format!(
+            "Run `gi track {branch}` to stack it on a parent first."
+        ));
+    };
+
+    if parent == graph.trunk() {
+        return Err(eyre::eyre!("Can't fold {} into the trunk.", branch))
+            .suggestion("Submit the branch with `gi submit` to merge it through a pull request.");
+    }
+    if !git_client.get_working_area()?.is_empty() {
+        return Err(eyre::eyre!("Can't fold a branch with uncommitted changes."))
+            .suggestion("Commit or stash the changes and try again.");
+    }
+
+    let parent_revision = git_client.get_branch_revision(&parent)?;
+    if !git_client.is_ancestor(&parent_revision, &branch)? {
+        return Err(eyre::eyre!(
+            "Branch {} isn't stacked on the latest revision of {}.",
+            branch,
+            parent
+        ))
+        .suggestion("Run `gi restack` first.");
+    }
+
+    // The parent is checked out before the operation starts, so that it ends on the parent too
+    let siblings = graph
+        .descendants(&parent)
+        .into_iter()
+        .filter(|b| *b != branch)
+        .collect();
+    git_client.checkout(&parent)?;
+    let operation = Operation::begin(
+        git_client,
+        "fold",
+        &[parent.clone(), branch.clone()],
+        siblings,
+    )?;
+
+    // The parent is fast-forwarded to the branch with HEAD detached on the branch, so that the
+    // working tree already matches the new revision of the parent when it's checked out again
+    let revision = git_client.get_branch_revision(&branch)?;
+    git_client.checkout(&revision)?;
+    git_client.update_ref(&format!("refs/heads/{parent}"), &revision)?;
+    git_client.checkout(&parent)?;
+    reparent_children(git_client, &branch, &parent)?;
+    delete_branch_metadata(git_client, &branch)?;
+    git_client.delete_branch(&branch)?;
+
+    let restacked = operation.run(git_client)?;
+    output::print(format!("Folded {branch} into {parent}."));
+    output::emit(json!({
+        "branch": parent,
+        "revision": git_client.get_branch_revision(&parent)?,
+        "folded": branch,
+        "restacked": restacked,
+    }));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{git_client::metadata::read_branch_metadata, testing::FakeGitClient};
+
+    fn stack_options() -> StackOptions {
+        StackOptions {
+            trunk: "main".to_string(),
+        }
+    }
+
+    #[test]
+    fn folding_moves_the_commits_and_children_to_the_parent() {
+        let git_client = FakeGitClient::new("main");
+        let lexer = git_client
+            .create_tracked_branch("Add the lexer", &["lexer"])
+            .unwrap();
+        let parser = git_client
+            .create_tracked_branch("Add the parser", &["parser"])
+            .unwrap();
+        let checker = git_client
+            .create_tracked_branch("Add the checker", &["checker"])
+            .unwrap();
+        git_client.checkout(&lexer).unwrap();
+        let docs = git_client
+            .create_tracked_branch("Document the lexer", &["docs"])
+            .unwrap();
+        git_client.checkout(&parser).unwrap();
+        let revision = git_client.get_branch_revision(&parser).unwrap();
+
+        fold(&git_client, &stack_options()).unwrap();
+
+        assert_eq!(git_client.get_current_branch().unwrap(), lexer);
+        assert_eq!(git_client.get_branch_revision(&lexer).unwrap(), revision);
+        assert!(!git_client.branches().contains(&parser));
+        assert!(read_branch_metadata(&git_client, &parser)
+            .unwrap()
+            .is_none());
+
+        let checker_metadata = read_branch_metadata(&git_client, &checker)
+            .unwrap()
+            .unwrap();
+        assert_eq!(checker_metadata.parent_branch_name(), lexer);
+        assert_eq!(
+            git_client.log(&docs),
+            vec![
+                "Document the lexer",
+                "Add the parser",
+                "Add the lexer",
+                "Initial commit"
+            ]
+        );
+    }
+
+    #[test]
+    fn branches_are_not_folded_into_the_trunk() {
+        let git_client = FakeGitClient::new("main");
+        let lexer = git_client
+            .create_tracked_branch("Add the lexer", &["lexer"])
+            .unwrap();
+        let revision = git_client.get_branch_revision("main").unwrap();
+
+        let error = fold(&git_client, &stack_options()).unwrap_err();
+
+        assert!(error.to_string().contains("trunk"), "{error}");
+        assert_eq!(git_client.get_branch_revision("main").unwrap(), revision);
+        assert!(git_client.branches().contains(&lexer));
+    }
+}
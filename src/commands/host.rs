@@ -0,0 +1,32 @@
+use eyre::Result;
+use serde_json::json;
+
+use crate::{
+    cli::output,
+    git_provider::SupportedProviders,
+    user::settings::{get_user_settings, HostSettings},
+};
+
+/// Maps a git host to the provider serving it, e.g. a GitHub Enterprise Server instance.
+///
+/// # Arguments
+///
+/// * `host` - The host of the remote, as it appears in its URL.
+/// * `provider` - The provider serving the host.
+/// * `api_url` - The base URL of the provider API, or `None` to use the default one of the host.
+pub fn host(host: &str, provider: SupportedProviders, api_url: Option<String>) -> Result<()> {
+    get_user_settings()?.set_host(
+        host,
+        HostSettings {
+            provider,
+            api_url: api_url.clone(),
+        },
+    )?;
+
+    output::print(format!(
+        "Repositories hosted on {host} will use {provider}."
+    ));
+    output::emit(json!({ "host": host, "provider": provider, "apiUrl": api_url }));
+
+    Ok(())
+}
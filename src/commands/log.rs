@@ -0,0 +1,126 @@
+use eyre::Result;
+use serde_json::{json, Value};
+
+use crate::{
+    cli::output,
+    commands::StackOptions,
+    git_client::GitClient,
+    stack::{needs_restack, StackGraph},
+};
+
+pub fn log(git_client: &dyn GitClient, options: &StackOptions) -> Result<()> {
+    let graph = StackGraph::load(git_client, options.trunk.clone())?;
+    let current_branch = git_client.get_current_branch();
+
+    if output::is_json() {
+        output::emit(graph_json(git_client, &graph, current_branch.as_deref()));
+        return Ok(());
+    }
+
+    let mut lines = Vec::new();
+    for root in graph.roots() {
+        lines.push(format_branch(
+            git_client,
+            &graph,
+            root,
+            current_branch.as_deref(),
+        ));
+        render_children(
+            git_client,
+            &graph,
+            root,
+            current_branch.as_deref(),
+            "",
+            &mut lines,
+        );
+    }
+
+    output::print(lines.join("\n"));
+
+    Ok(())
+}
+
+/// Describes every tracked branch of the stack graph, parents before children.
+fn graph_json(
+    git_client: &dyn GitClient,
+    graph: &StackGraph,
+    current_branch: Option<&str>,
+) -> Value {
+    let branches = graph
+        .topological_order()
+        .iter()
+        .filter_map(|branch| {
+            let metadata = graph.metadata(branch)?;
+
+            Some(json!({
+                "name": branch,
+                "metadata": metadata,
+                "children": graph.children(branch),
+                "needsRestack": needs_restack(git_client, metadata),
+            }))
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "trunk": graph.trunk(),
+        "roots": graph.roots(),
+        "currentBranch": current_branch,
+        "branches": branches,
+    })
+}
+
+/// Renders the children of a branch as an indented tree, appending one line per branch.
+fn render_children(
+    git_client: &dyn GitClient,
+    graph: &StackGraph,
+    branch: &str,
+    current_branch: Option<&str>,
+    prefix: &str,
+    lines: &mut Vec<String>,
+) {
+    let children = graph.children(branch);
+
+    for (index, child) in children.iter().enumerate() {
+        let is_last = index == children.len() - 1;
+        let (connector, indent) = if is_last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        lines.push(format!(
+            "{prefix}{connector}{}",
+            format_branch(git_client, graph, child, current_branch)
+        ));
+
+        render_children(
+            git_client,
+            graph,
+            child,
+            current_branch,
+            &format!("{prefix}{indent}"),
+            lines,
+        );
+    }
+}
+
+fn format_branch(
+    git_client: &dyn GitClient,
+    graph: &StackGraph,
+    branch: &str,
+    current_branch: Option<&str>,
+) -> String {
+    let mut line = branch.to_string();
+
+    if current_branch == Some(branch) {
+        line.push_str(" (current)");
+    }
+
+    if let Some(metadata) = graph.metadata(branch) {
+        if needs_restack(git_client, metadata) {
+            line.push_str(" (needs restack)");
+        }
+    }
+
+    line
+}
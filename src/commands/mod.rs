@@ -1,2 +1,33 @@
 pub mod create;
+pub mod fold;
+pub mod host;
+pub mod log;
+pub mod modify;
+pub mod move_branch;
+pub mod navigate;
+pub mod operation;
+pub mod restack;
+pub mod split;
+pub mod squash;
 pub mod submit;
+pub mod sync;
+pub mod track;
+
+use eyre::Result;
+
+use crate::project::settings::get_project_settings;
+
+/// The settings every command working on the stacks of the repository needs.
+pub struct StackOptions {
+    /// The branch every stack is rooted at.
+    pub trunk: String,
+}
+
+impl StackOptions {
+    /// Creates stack options from the project settings, asking the user for the missing ones.
+    pub fn from_project_settings() -> Result<Self> {
+        Ok(Self {
+            trunk: get_project_settings()?.get_trunk()?,
+        })
+    }
+}
@@ -0,0 +1,188 @@
+use color_eyre::Section;
+use eyre::{Ok, Result};
+use serde_json::json;
+
+use crate::{
+    cli::output,
+    commands::{
+        create::{commit, stage_changes, CommitOptions},
+        StackOptions,
+    },
+    git_client::{CommitStatus, GitClient},
+    stack::{operation::Operation, StackGraph},
+};
+
+pub fn modify(
+    git_client: &dyn GitClient,
+    new_commit: bool,
+    options: &CommitOptions,
+    stack_options: &StackOptions,
+) -> Result<()> {
+    let Some(branch) = git_client.get_current_branch() else {
+        return Err(
+            eyre::eyre!("Can't modify a stacked commit without a current branch.")
+                .suggestion("Checkout onto a branch and try again."),
+        );
+    };
+
+    Operation::ensure_none_in_progress(git_client)?;
+
+    let graph = StackGraph::load(git_client, stack_options.trunk.clone())?;
+    if branch == graph.trunk() {
+        return Err(eyre::eyre!("Can't modify the trunk."))
+            .suggestion("Run `gi create` to commit the changes on a new branch stacked on it.");
+    }
+
+    let working_area = git_client.get_working_area()?;
+    if working_area.is_empty() {
+        if new_commit {
+            return Err(
+                eyre::eyre!("Can't add a new commit without changes in the working area.")
+                    .suggestion("Make some changes to your files and try again."),
+            );
+        }
+    } else if !stage_changes(git_client, &working_area, options)? {
+        return Ok(());
+    }
+
+    let descendants = graph.descendants(&branch);
+
+    // The branch is snapshotted before its commits are rewritten, so that `gi abort` can restore it
+    let operation = Operation::begin(
+        git_client,
+        "modify",
+        std::slice::from_ref(&branch),
+        descendants,
+    )?;
+
+    let commit_status = if new_commit {
+        commit(git_client, options)
+    } else if options.message.is_some() || !options.interactive {
+        // Without an editor to open, the amended commit keeps its message unless a new one is given
+        git_client
+            .amend_with_message(options.message.as_deref())
+            .map(|_| CommitStatus::Success)
+    } else {
+        git_client.interactive_amend()
+    };
+
+    if !matches!(commit_status.as_ref().ok(), Some(CommitStatus::Success)) {
+        operation.rollback(git_client)?;
+        commit_status?;
+
+        output::emit(json!({ "branch": branch, "aborted": true }));
+        return Ok(());
+    }
+
+    let restacked = operation.run(git_client)?;
+    output::emit(json!({
+        "branch": branch,
+        "revision": git_client.get_branch_revision(&branch)?,
+        "restacked": restacked,
+    }));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{git_client::metadata::read_branch_metadata, testing::FakeGitClient};
+
+    fn options(message: Option<&str>) -> CommitOptions {
+        CommitOptions {
+            all: false,
+            message: message.map(str::to_string),
+            interactive: false,
+        }
+    }
+
+    fn stack_options() -> StackOptions {
+        StackOptions {
+            trunk: "main".to_string(),
+        }
+    }
+
+    /// Creates a stack of two branches and checks out the bottom one, returning them bottom first.
+    fn stack(git_client: &FakeGitClient) -> (String, String) {
+        let lexer = git_client
+            .create_tracked_branch("Add the lexer", &["lexer"])
+            .unwrap();
+        let parser = git_client
+            .create_tracked_branch("Add the parser", &["parser"])
+            .unwrap();
+        git_client.checkout(&lexer).unwrap();
+
+        (lexer, parser)
+    }
+
+    #[test]
+    fn amending_keeps_the_message_and_restacks_the_branches_above() {
+        let git_client = FakeGitClient::new("main");
+        let (lexer, parser) = stack(&git_client);
+        let revision = git_client.get_branch_revision(&lexer).unwrap();
+
+        git_client.stage_files(&["lexer"]);
+        modify(&git_client, false, &options(None), &stack_options()).unwrap();
+
+        let amended = git_client.get_branch_revision(&lexer).unwrap();
+        assert_ne!(amended, revision);
+        assert_eq!(
+            git_client.log(&lexer),
+            vec!["Add the lexer", "Initial commit"]
+        );
+        assert_eq!(
+            git_client.log(&parser),
+            vec!["Add the parser", "Add the lexer", "Initial commit"]
+        );
+        assert_eq!(
+            read_branch_metadata(&git_client, &parser)
+                .unwrap()
+                .unwrap()
+                .parent_branch_revision(),
+            amended
+        );
+        assert_eq!(git_client.get_current_branch().unwrap(), lexer);
+    }
+
+    #[test]
+    fn a_new_commit_is_added_below_the_branches_above() {
+        let git_client = FakeGitClient::new("main");
+        let (lexer, parser) = stack(&git_client);
+
+        git_client.stage_files(&["lexer"]);
+        modify(
+            &git_client,
+            true,
+            &options(Some("Fix the lexer")),
+            &stack_options(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            git_client.log(&lexer),
+            vec!["Fix the lexer", "Add the lexer", "Initial commit"]
+        );
+        assert_eq!(
+            git_client.log(&parser),
+            vec![
+                "Add the parser",
+                "Fix the lexer",
+                "Add the lexer",
+                "Initial commit"
+            ]
+        );
+    }
+
+    #[test]
+    fn the_trunk_is_not_modified() {
+        let git_client = FakeGitClient::new("main");
+        let revision = git_client.get_branch_revision("main").unwrap();
+
+        git_client.stage_files(&["lexer"]);
+        let error = modify(&git_client, false, &options(None), &stack_options()).unwrap_err();
+
+        assert!(error.to_string().contains("trunk"), "{error}");
+        assert_eq!(git_client.get_branch_revision("main").unwrap(), revision);
+    }
+}
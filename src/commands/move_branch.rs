@@ -0,0 +1,216 @@
+use color_eyre::Section;
+use eyre::{Ok, OptionExt, Result};
+use serde_json::json;
+
+use crate::{
+    cli::output,
+    commands::{track::ensure_branch_exists, StackOptions},
+    git_client::{
+        metadata::{create_branch_metadata, get_all_branch_metadata, read_branch_metadata},
+        GitClient,
+    },
+    stack::{operation::Operation, reparent_children, StackGraph},
+};
+
+/// Stacks the current branch on another parent, rebasing the commits it made since its recorded
+/// parent revision onto the new parent.
+///
+/// Unless the descendants move along, the branches stacked on top of the current one stay where
+/// they are, restacked onto its former parent.
+///
+/// # Arguments
+///
+/// * `git_client` - The git client of the repository.
+/// * `onto` - The branch to stack the current one on.
+/// * `descendants` - Whether the branches stacked on top of the current one move along with it.
+/// * `options` - The trunk the stacks are rooted at.
+pub fn move_branch(
+    git_client: &dyn GitClient,
+    onto: &str,
+    descendants: bool,
+    options: &StackOptions,
+) -> Result<()> {
+    Operation::ensure_none_in_progress(git_client)?;
+
+    let branch = git_client
+        .get_current_branch()
+        .ok_or_eyre("Failed to get the current branch")
+        .suggestion("Check out the branch to move.")?;
+    ensure_branch_exists(git_client, onto)?;
+
+    let trunk = options.trunk.clone();
+    let mut all_metadata = get_all_branch_metadata(git_client)?;
+    let graph = StackGraph::new(trunk.clone(), all_metadata.clone());
+    let Some(metadata) = graph.metadata(&branch).cloned() else {
+        return Err(eyre::eyre!("Branch {} is not tracked.", branch)).suggestion(format!(
+            "Run `gi track {branch}` to stack it on a parent first."
+        ));
+    };
+
+    let stacked_on_top = graph.descendants(&branch);
+
+    // Lays out the stacks as they'll be after the move, to restack parents before children
+    let old_parent = metadata.parent_branch_name().to_string();
+    if !descendants {
+        for child in graph.children(&branch) {
+            if let Some(child_metadata) = all_metadata.get_mut(child) {
+                child_metadata.set_parent_branch_name(old_parent.clone());
+            }
+        }
+    }
+    let mut moved_metadata = metadata.clone();
+    moved_metadata.set_parent_branch_name(onto.to_string());
+    all_metadata.insert(branch.clone(), moved_metadata.clone());
+
+    // A branch moved onto itself, or onto a descendant moving along, would be its own ancestor
+    let moved = StackGraph::new(trunk, all_metadata);
+    if moved.ancestors(&branch).contains(&branch) {
+        return Err(eyre::eyre!(
+            "Branch {} can't be moved onto {}, which moves along with it.",
+            branch,
+            onto
+        ))
+        .suggestion("Pick a branch outside of the moved ones.");
+    }
+
+    let pending = moved
+        .topological_order()
+        .into_iter()
+        .filter(|b| *b == branch || stacked_on_top.contains(b))
+        .collect();
+    let operation = Operation::begin(git_client, "move", &[], pending)?;
+
+    if !descendants {
+        reparent_children(git_client, &branch, &old_parent)?;
+    }
+    create_branch_metadata(git_client, branch.clone(), &moved_metadata)?;
+
+    let restacked = operation.run(git_client)?;
+    output::print(format!("Moved {branch} from {old_parent} onto {onto}."));
+    output::emit(json!({
+        "branch": branch,
+        "metadata": read_branch_metadata(git_client, &branch)?,
+        "restacked": restacked,
+    }));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeGitClient;
+
+    fn options() -> StackOptions {
+        StackOptions {
+            trunk: "main".to_string(),
+        }
+    }
+
+    /// Creates a stack of three branches on top of the trunk, returning them bottom first.
+    fn stack(git_client: &FakeGitClient) -> [String; 3] {
+        ["lexer", "parser", "checker"].map(|file| {
+            git_client
+                .create_tracked_branch(&format!("Add the {file}"), &[file])
+                .unwrap()
+        })
+    }
+
+    fn parent(git_client: &FakeGitClient, branch: &str) -> String {
+        read_branch_metadata(git_client, branch)
+            .unwrap()
+            .unwrap()
+            .parent_branch_name()
+            .to_string()
+    }
+
+    #[test]
+    fn the_branches_above_stay_on_the_former_parent() {
+        let git_client = FakeGitClient::new("main");
+        let [lexer, parser, checker] = stack(&git_client);
+        git_client.checkout(&parser).unwrap();
+
+        move_branch(&git_client, "main", false, &options()).unwrap();
+
+        assert_eq!(parent(&git_client, &parser), "main");
+        assert_eq!(parent(&git_client, &checker), lexer);
+        assert_eq!(
+            git_client.log(&parser),
+            vec!["Add the parser", "Initial commit"]
+        );
+        assert_eq!(
+            git_client.log(&checker),
+            vec!["Add the checker", "Add the lexer", "Initial commit"]
+        );
+        assert_eq!(git_client.get_current_branch().unwrap(), parser);
+    }
+
+    #[test]
+    fn the_descendants_move_along_when_asked() {
+        let git_client = FakeGitClient::new("main");
+        let [lexer, parser, checker] = stack(&git_client);
+        git_client.checkout("main").unwrap();
+        let docs = git_client
+            .create_tracked_branch("Add the docs", &["docs"])
+            .unwrap();
+        git_client.checkout(&parser).unwrap();
+
+        move_branch(&git_client, &docs, true, &options()).unwrap();
+
+        assert_eq!(parent(&git_client, &parser), docs);
+        assert_eq!(parent(&git_client, &checker), parser);
+        assert_eq!(
+            git_client.log(&checker),
+            vec![
+                "Add the checker",
+                "Add the parser",
+                "Add the docs",
+                "Initial commit"
+            ]
+        );
+        assert_eq!(
+            git_client.log(&lexer),
+            vec!["Add the lexer", "Initial commit"]
+        );
+    }
+
+    #[test]
+    fn a_branch_can_move_onto_a_descendant_left_behind() {
+        let git_client = FakeGitClient::new("main");
+        let [lexer, parser, checker] = stack(&git_client);
+        git_client.checkout(&parser).unwrap();
+
+        move_branch(&git_client, &checker, false, &options()).unwrap();
+
+        assert_eq!(parent(&git_client, &checker), lexer);
+        assert_eq!(parent(&git_client, &parser), checker);
+        assert_eq!(
+            git_client.log(&parser),
+            vec![
+                "Add the parser",
+                "Add the checker",
+                "Add the lexer",
+                "Initial commit"
+            ]
+        );
+    }
+
+    #[test]
+    fn moves_that_would_stack_a_branch_on_itself_are_refused() {
+        let git_client = FakeGitClient::new("main");
+        let [_, parser, checker] = stack(&git_client);
+        git_client.checkout(&parser).unwrap();
+        let revisions = [&parser, &checker].map(|b| git_client.get_branch_revision(b).unwrap());
+
+        for (onto, descendants) in [(&parser, false), (&checker, true)] {
+            let error = move_branch(&git_client, onto, descendants, &options()).unwrap_err();
+            assert!(error.to_string().contains("moves along with it"), "{error}");
+        }
+
+        assert_eq!(
+            [&parser, &checker].map(|b| git_client.get_branch_revision(b).unwrap()),
+            revisions
+        );
+        assert_eq!(parent(&git_client, &checker), parser);
+    }
+}
@@ -0,0 +1,227 @@
+use color_eyre::Section;
+use dialoguer::{theme::ColorfulTheme, FuzzySelect};
+use eyre::{Ok, OptionExt, Result};
+use serde_json::json;
+
+use crate::{
+    cli::{interactive::ensure_interactive, output},
+    commands::StackOptions,
+    git_client::GitClient,
+    stack::StackGraph,
+};
+
+/// Checks out the child of the current branch, `steps` times.
+pub fn up(git_client: &dyn GitClient, steps: usize, options: &StackOptions) -> Result<()> {
+    let (graph, mut branch) = load_stack(git_client, options)?;
+
+    for _ in 0..steps {
+        match select_child(&graph, &branch)? {
+            Some(child) => branch = child,
+            None => break,
+        }
+    }
+
+    checkout(git_client, &branch)
+}
+
+/// Checks out the parent of the current branch, `steps` times.
+pub fn down(git_client: &dyn GitClient, steps: usize, options: &StackOptions) -> Result<()> {
+    let (graph, mut branch) = load_stack(git_client, options)?;
+
+    if !graph.is_tracked(&branch) {
+        return Err(eyre::eyre!("Branch {} is not part of a stack.", branch))
+            .suggestion("Create stacked branches on top of it with `gi create`.");
+    }
+
+    for _ in 0..steps {
+        match graph.parent(&branch) {
+            Some(parent) => branch = parent.to_string(),
+            None => break,
+        }
+    }
+
+    checkout(git_client, &branch)
+}
+
+/// Checks out the tip of the current stack.
+pub fn top(git_client: &dyn GitClient, options: &StackOptions) -> Result<()> {
+    let (graph, mut branch) = load_stack(git_client, options)?;
+
+    while let Some(child) = select_child(&graph, &branch)? {
+        branch = child;
+    }
+
+    checkout(git_client, &branch)
+}
+
+/// Checks out the branch at the base of the current stack, right above the trunk.
+pub fn bottom(git_client: &dyn GitClient, options: &StackOptions) -> Result<()> {
+    let (graph, branch) = load_stack(git_client, options)?;
+
+    if !graph.is_tracked(&branch) {
+        return Err(eyre::eyre!("Branch {} is not part of a stack.", branch))
+            .suggestion("Use `gi up` to move from the trunk into a stack.");
+    }
+
+    let bottom = graph
+        .ancestors(&branch)
+        .into_iter()
+        .next()
+        .unwrap_or(branch);
+
+    checkout(git_client, &bottom)
+}
+
+fn load_stack(git_client: &dyn GitClient, options: &StackOptions) -> Result<(StackGraph, String)> {
+    let branch = git_client
+        .get_current_branch()
+        .ok_or_eyre("Failed to get the current branch")
+        .suggestion("Check whether you are checked out onto a branch")?;
+    let graph = StackGraph::load(git_client, options.trunk.clone())?;
+
+    Ok((graph, branch))
+}
+
+/// Returns the child of a branch, asking the user to pick one when there are several.
+fn select_child(graph: &StackGraph, branch: &str) -> Result<Option<String>> {
+    let children = graph.children(branch);
+
+    let child = match children.len() {
+        0 => None,
+        1 => Some(children[0].to_string()),
+        _ => {
+            ensure_interactive(
+                &format!("to know which child of {branch} to go to"),
+                "Check out the child with `git checkout <branch>` instead.",
+            )?;
+
+            let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!(
+                    "{branch} has multiple children. Which one to go to?"
+                ))
+                .default(0)
+                .items(&children)
+                .interact()?;
+
+            Some(children[selection].to_string())
+        }
+    };
+
+    Ok(child)
+}
+
+fn checkout(git_client: &dyn GitClient, branch: &str) -> Result<()> {
+    if git_client.get_current_branch().as_deref() == Some(branch) {
+        output::print(format!("Already on {branch}."));
+        output::emit(json!({ "branch": branch }));
+        return Ok(());
+    }
+
+    git_client.checkout(branch)?;
+    output::print(format!("Checked out {branch}."));
+    output::emit(json!({ "branch": branch }));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeGitClient;
+
+    fn options() -> StackOptions {
+        StackOptions {
+            trunk: "main".to_string(),
+        }
+    }
+
+    /// Creates a stack of three branches on top of the trunk, returning them bottom first.
+    fn stack(git_client: &FakeGitClient) -> [String; 3] {
+        ["lexer", "parser", "checker"].map(|file| {
+            git_client
+                .create_tracked_branch(&format!("Add the {file}"), &[file])
+                .unwrap()
+        })
+    }
+
+    fn current_branch(git_client: &FakeGitClient) -> String {
+        git_client.get_current_branch().unwrap()
+    }
+
+    #[test]
+    fn up_goes_from_the_trunk_into_the_stack_and_stops_at_its_top() {
+        let git_client = FakeGitClient::new("main");
+        let [lexer, parser, checker] = stack(&git_client);
+        git_client.checkout("main").unwrap();
+
+        up(&git_client, 1, &options()).unwrap();
+        assert_eq!(current_branch(&git_client), lexer);
+
+        up(&git_client, 1, &options()).unwrap();
+        assert_eq!(current_branch(&git_client), parser);
+
+        up(&git_client, 5, &options()).unwrap();
+        assert_eq!(current_branch(&git_client), checker);
+    }
+
+    #[test]
+    fn down_stops_at_the_trunk() {
+        let git_client = FakeGitClient::new("main");
+        let [lexer, _, _] = stack(&git_client);
+
+        down(&git_client, 2, &options()).unwrap();
+        assert_eq!(current_branch(&git_client), lexer);
+
+        down(&git_client, 5, &options()).unwrap();
+        assert_eq!(current_branch(&git_client), "main");
+
+        let error = down(&git_client, 1, &options()).unwrap_err();
+        assert!(error.to_string().contains("not part of a stack"), "{error}");
+    }
+
+    #[test]
+    fn top_and_bottom_go_to_the_ends_of_the_stack() {
+        let git_client = FakeGitClient::new("main");
+        let [lexer, parser, checker] = stack(&git_client);
+        git_client.checkout(&parser).unwrap();
+
+        top(&git_client, &options()).unwrap();
+        assert_eq!(current_branch(&git_client), checker);
+
+        bottom(&git_client, &options()).unwrap();
+        assert_eq!(current_branch(&git_client), lexer);
+
+        bottom(&git_client, &options()).unwrap();
+        assert_eq!(current_branch(&git_client), lexer);
+    }
+
+    #[test]
+    fn bottom_needs_a_branch_of_a_stack() {
+        let git_client = FakeGitClient::new("main");
+        stack(&git_client);
+        git_client.checkout("main").unwrap();
+
+        let error = bottom(&git_client, &options()).unwrap_err();
+
+        assert!(error.to_string().contains("not part of a stack"), "{error}");
+        assert_eq!(current_branch(&git_client), "main");
+    }
+
+    #[test]
+    fn going_up_a_branch_with_several_children_needs_a_terminal() {
+        let git_client = FakeGitClient::new("main");
+        let [lexer, _, _] = stack(&git_client);
+        git_client.checkout(&lexer).unwrap();
+        git_client
+            .create_tracked_branch("Document the lexer", &["docs"])
+            .unwrap();
+        git_client.checkout(&lexer).unwrap();
+
+        let error = up(&git_client, 1, &options()).unwrap_err();
+        assert!(error.to_string().contains("non-interactively"), "{error}");
+
+        let error = top(&git_client, &options()).unwrap_err();
+        assert!(error.to_string().contains("non-interactively"), "{error}");
+        assert_eq!(current_branch(&git_client), lexer);
+    }
+}
@@ -0,0 +1,30 @@
+use color_eyre::Section;
+use eyre::{OptionExt, Result};
+use serde_json::json;
+
+use crate::{cli::output, git_client::GitClient, stack::operation::Operation};
+
+pub fn continue_operation(git_client: &dyn GitClient) -> Result<()> {
+    let operation = Operation::load(git_client)?
+        .ok_or_eyre("There is no gi operation to continue.")
+        .suggestion("Use `git rebase --continue` for rebases started outside of gi.")?;
+
+    let name = operation.name().to_string();
+    let restacked = operation.resume(git_client)?;
+    output::emit(json!({ "operation": name, "restacked": restacked }));
+
+    Ok(())
+}
+
+pub fn abort_operation(git_client: &dyn GitClient) -> Result<()> {
+    let operation = Operation::load(git_client)?
+        .ok_or_eyre("There is no gi operation to abort.")
+        .suggestion("Use `git rebase --abort` for rebases started outside of gi.")?;
+
+    let name = operation.name().to_string();
+    operation.rollback(git_client)?;
+    output::print(format!("Aborted gi {name}, every branch was restored."));
+    output::emit(json!({ "operation": name, "aborted": true }));
+
+    Ok(())
+}
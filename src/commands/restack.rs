@@ -0,0 +1,19 @@
+use eyre::Result;
+use serde_json::json;
+
+use crate::{
+    cli::output,
+    commands::StackOptions,
+    git_client::GitClient,
+    stack::{operation::Operation, StackGraph},
+};
+
+pub fn restack(git_client: &dyn GitClient, options: &StackOptions) -> Result<()> {
+    let graph = StackGraph::load(git_client, options.trunk.clone())?;
+    let branches = graph.topological_order();
+
+    let restacked = Operation::begin(git_client, "restack", &[], branches)?.run(git_client)?;
+    output::emit(json!({ "restacked": restacked }));
+
+    Ok(())
+}
@@ -0,0 +1,399 @@
+use clap::ValueEnum;
+use color_eyre::Section;
+use dialoguer::{theme::ColorfulTheme, Select};
+use eyre::{Ok, OptionExt, Result};
+use serde_json::json;
+
+use crate::{
+    cli::{interactive::is_interactive, output},
+    commands::create::{commit, format_commit_title, CommitOptions},
+    git_client::{
+        metadata::{create_branch_metadata, BranchMetadata},
+        CommitStatus, GitClient,
+    },
+    project::settings::get_project_settings,
+    stack::{operation::Operation, StackGraph},
+};
+
+/// How `gi split` divides the commits of the current branch into stacked branches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SplitMode {
+    /// Every commit becomes its own branch
+    Commit,
+    /// The hunks of the branch are picked into successive new branches
+    Hunk,
+}
+
+/// How the current branch is split.
+pub struct SplitOptions {
+    /// How to divide the branch, or `None` to ask the user.
+    pub mode: Option<SplitMode>,
+    /// Whether the user can be asked for the mode, the hunks and the commit messages.
+    pub interactive: bool,
+    pub trunk: String,
+}
+
+impl SplitOptions {
+    /// Creates split options from the project settings, asking the user for the missing ones.
+    pub fn from_project_settings(mode: Option<SplitMode>) -> Result<Self> {
+        Ok(Self {
+            mode,
+            interactive: is_interactive(),
+            trunk: get_project_settings()?.get_trunk()?,
+        })
+    }
+}
+
+/// The branch made out of one of the commits of a split branch.
+struct Split {
+    branch: String,
+    revision: String,
+}
+
+/// Splits the current branch into a stack of branches, each one stacked on the previous one.
+///
+/// The current branch keeps the last part, so that its pull request and the branches stacked on
+/// top of it stay where they are; the other parts get branches named after their commit title,
+/// like `gi create` names them.
+///
+/// # Arguments
+///
+/// * `git_client` - The git client of the repository.
+/// * `options` - How to divide the branch, and whether the user can be asked.
+pub fn split(git_client: &dyn GitClient, options: &SplitOptions) -> Result<()> {
+    Operation::ensure_none_in_progress(git_client)?;
+
+    let branch = git_client
+        .get_current_branch()
+        .ok_or_eyre("Failed to get the current branch")
+        .suggestion("Check out the branch to split.")?;
+    let graph = StackGraph::load(git_client, options.trunk.clone())?;
+    let Some(metadata) = graph.metadata(&branch).cloned() else {
+        return Err(eyre::eyre!("Branch {} is not tracked.", branch)).suggestion(format!(
+            "Run `gi track {branch}` to stack it on a parent first."
+        ));
+    };
+
+    if !git_client.get_working_area()?.is_empty() {
+        return Err(eyre::eyre!(
+            "Can't split a branch with uncommitted changes."
+        ))
+        .suggestion("Commit or stash the changes and try again.");
+    }
+
+    let mode = select_mode(options)?;
+    let commits = match mode {
+        SplitMode::Commit => git_client.list_commits(metadata.parent_branch_revision(), &branch)?,
+        SplitMode::Hunk => {
+            let Some(commits) = pick_hunks(git_client, &branch, &metadata, options)? else {
+                output::emit(json!({ "branch": branch, "aborted": true }));
+                return Ok(());
+            };
+            commits
+        }
+    };
+
+    let Some((last, parts)) = commits.split_last() else {
+        return Err(eyre::eyre!("Branch {} has no commits to split.", branch))
+            .suggestion("Run `gi restack` if its parent was rewritten.");
+    };
+    if parts.is_empty() && mode == SplitMode::Commit {
+        return Err(eyre::eyre!("Branch {} has a single commit.", branch))
+            .suggestion("Split it by hunk with `gi split --by hunk`.");
+    }
+    if parts.is_empty() {
+        return Err(eyre::eyre!(
+            "Every hunk of {} was kept in the same branch.",
+            branch
+        ))
+        .suggestion("Pick only some of the hunks for each new branch.");
+    }
+
+    let mut splits = Vec::new();
+    for revision in parts {
+        let title = git_client.get_commit_title(revision)?;
+        let name = unique_branch_name(git_client, &format_commit_title(title)?, &splits);
+        splits.push(Split {
+            branch: name,
+            revision: revision.clone(),
+        });
+    }
+
+    // The new branches are snapshotted as missing, so that `gi abort` deletes them
+    let mut affected = splits.iter().map(|s| s.branch.clone()).collect::<Vec<_>>();
+    affected.push(branch.clone());
+    let operation = Operation::begin(git_client, "split", &affected, graph.descendants(&branch))?;
+
+    let mut parent = metadata.parent_branch_name().to_string();
+    let mut parent_revision = metadata.parent_branch_revision().to_string();
+    for split in &splits {
+        git_client.update_ref(&format!("refs/heads/{}", split.branch), &split.revision)?;
+
+        let split_metadata = BranchMetadata::new(parent, parent_revision);
+        create_branch_metadata(git_client, split.branch.clone(), &split_metadata)?;
+
+        parent = split.branch.clone();
+        parent_revision = split.revision.clone();
+    }
+
+    // Splitting by hunk rewrote the branch, so the branches on top of it need restacking
+    git_client.update_ref(&format!("refs/heads/{branch}"), last)?;
+    let mut metadata = metadata;
+    metadata.set_parent_branch_name(parent);
+    metadata.set_parent_branch_revision(parent_revision);
+    create_branch_metadata(git_client, branch.clone(), &metadata)?;
+
+    let restacked = operation.run(git_client)?;
+
+    let mut branches = splits.into_iter().map(|s| s.branch).collect::<Vec<_>>();
+    branches.push(branch.clone());
+    output::print(format!(
+        "Split {branch} into {} stacked branches: {}.",
+        branches.len(),
+        branches.join(", ")
+    ));
+    output::emit(json!({ "branches": branches, "restacked": restacked }));
+
+    Ok(())
+}
+
+/// Asks the user how to split the branch, unless the options already tell.
+fn select_mode(options: &SplitOptions) -> Result<SplitMode> {
+    if let Some(mode) = options.mode {
+        return Ok(mode);
+    }
+
+    if !options.interactive {
+        return Err(eyre::eyre!(
+            "Can't ask how to split the branch when running non-interactively."
+        ))
+        .suggestion("Pass the mode with `--by commit` or `--by hunk`.");
+    }
+
+    let items = ["By commit", "By hunk"];
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("How would you like to split the branch?")
+        .items(&items)
+        .default(0)
+        .interact()?;
+
+    Ok([SplitMode::Commit, SplitMode::Hunk][selection])
+}
+
+/// Lets the user pick the hunks of the branch into successive commits, the changes left over
+/// making up the last one with the message of the branch tip.
+///
+/// The commits are made on a temporary branch, which is removed afterwards.
+///
+/// # Returns
+///
+/// A `Result` containing the new commits, oldest first, or `None` if the user aborted a commit,
+/// in which case the branch is left untouched.
+fn pick_hunks(
+    git_client: &dyn GitClient,
+    branch: &str,
+    metadata: &BranchMetadata,
+    options: &SplitOptions,
+) -> Result<Option<Vec<String>>> {
+    if !options.interactive {
+        return Err(eyre::eyre!(
+            "Can't pick the hunks to split when running non-interactively."
+        ))
+        .suggestion("Split the branch by commit with `gi split --by commit`.");
+    }
+
+    let original_revision = git_client.get_branch_revision(branch)?;
+    let message = format!(
+        "{}\n\n{}",
+        git_client.get_commit_title(branch)?,
+        git_client.get_commit_body(branch)?
+    );
+
+    let temp_branch = git_client.create_branch("gi_temp_branch")?;
+    git_client.checkout(&temp_branch)?;
+
+    // Whatever happens to the commits, the branch must be checked out as it was
+    let commits = pick_hunks_on(git_client, metadata, &message);
+    if !matches!(commits.as_ref().ok(), Some(Some(_))) {
+        git_client.reset(&original_revision)?;
+        git_client.checkout(branch)?;
+        git_client.delete_branch(&temp_branch)?;
+
+        return commits;
+    }
+
+    git_client.checkout(branch)?;
+    git_client.delete_branch(&temp_branch)?;
+
+    commits
+}
+
+fn pick_hunks_on(
+    git_client: &dyn GitClient,
+    metadata: &BranchMetadata,
+    message: &str,
+) -> Result<Option<Vec<String>>> {
+    git_client.reset(metadata.parent_branch_revision())?;
+
+    let commit_options = CommitOptions {
+        all: false,
+        message: None,
+        interactive: true,
+    };
+    let mut commits = Vec::new();
+    loop {
+        output::print(format!(
+            "Pick the hunks of branch #{}, or none to keep the rest together.",
+            commits.len() + 1
+        ));
+        git_client.interactive_add()?;
+        if !git_client.get_working_area()?.has_staged_changes() {
+            break;
+        }
+
+        if let CommitStatus::Aborted = commit(git_client, &commit_options)? {
+            return Ok(None);
+        }
+        commits.push(git_client.get_branch_revision("HEAD")?);
+
+        if git_client.get_working_area()?.is_empty() {
+            return Ok(Some(commits));
+        }
+    }
+
+    git_client.add_all()?;
+    git_client.commit_with_message(message)?;
+    commits.push(git_client.get_branch_revision("HEAD")?);
+
+    Ok(Some(commits))
+}
+
+/// Appends a number to the branch name if a branch, or another part of the split, already has it.
+fn unique_branch_name(git_client: &dyn GitClient, name: &str, splits: &[Split]) -> String {
+    let is_taken = |candidate: &str| {
+        splits.iter().any(|s| s.branch == candidate)
+            || git_client
+                .get_branch_revision(&format!("refs/heads/{candidate}"))
+                .is_ok()
+    };
+
+    let mut unique = name.to_string();
+    let mut suffix = 0;
+    while is_taken(&unique) {
+        suffix += 1;
+        unique = format!("{name}{suffix}");
+    }
+
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{git_client::metadata::read_branch_metadata, testing::FakeGitClient};
+
+    fn options(mode: SplitMode, interactive: bool) -> SplitOptions {
+        SplitOptions {
+            mode: Some(mode),
+            interactive,
+            trunk: "main".to_string(),
+        }
+    }
+
+    #[test]
+    fn splitting_by_commit_stacks_every_commit_on_the_previous_one() {
+        let git_client = FakeGitClient::new("main");
+        let branch = git_client
+            .create_tracked_branch("Add the lexer", &["lexer"])
+            .unwrap();
+        git_client.commit("Add the parser", &["parser"]).unwrap();
+        git_client.commit("Add the checker", &["checker"]).unwrap();
+        let revision = git_client.get_branch_revision(&branch).unwrap();
+
+        split(&git_client, &options(SplitMode::Commit, false)).unwrap();
+
+        // The branch keeps its name and its last commit, the others get branches of their own
+        let metadata = read_branch_metadata(&git_client, &branch).unwrap().unwrap();
+        let parser = metadata.parent_branch_name().to_string();
+        let parser_metadata = read_branch_metadata(&git_client, &parser).unwrap().unwrap();
+        let lexer = parser_metadata.parent_branch_name().to_string();
+        let lexer_metadata = read_branch_metadata(&git_client, &lexer).unwrap().unwrap();
+
+        assert!(parser.ends_with("-Add_the_parser"), "{parser}");
+        assert!(lexer.ends_with("-Add_the_lexer1"), "{lexer}");
+        assert_eq!(git_client.branches().len(), 4);
+        assert_eq!(
+            git_client.log(&lexer),
+            vec!["Add the lexer", "Initial commit"]
+        );
+        assert_eq!(git_client.log(&parser)[0], "Add the parser");
+        assert_eq!(lexer_metadata.parent_branch_name(), "main");
+        assert_eq!(
+            metadata.parent_branch_revision(),
+            git_client.get_branch_revision(&parser).unwrap()
+        );
+        assert_eq!(git_client.get_branch_revision(&branch).unwrap(), revision);
+        assert_eq!(git_client.get_current_branch().unwrap(), branch);
+    }
+
+    #[test]
+    fn splitting_by_hunk_commits_the_picked_hunks_on_new_branches() {
+        let git_client = FakeGitClient::new("main");
+        let branch = git_client
+            .create_tracked_branch("Add the front end", &["lexer", "parser"])
+            .unwrap();
+        let top = git_client
+            .create_tracked_branch("Add the checker", &["checker"])
+            .unwrap();
+        git_client.checkout(&branch).unwrap();
+
+        git_client.queue_hunk_selection(&["lexer"]);
+        git_client.queue_commit_message("Add the lexer", "");
+        split(&git_client, &options(SplitMode::Hunk, true)).unwrap();
+
+        let lexer = git_client
+            .branches()
+            .into_iter()
+            .find(|b| b.ends_with("-Add_the_lexer"))
+            .unwrap();
+        assert!(!git_client
+            .branches()
+            .contains(&"gi_temp_branch".to_string()));
+        assert_eq!(
+            git_client.log(&lexer),
+            vec!["Add the lexer", "Initial commit"]
+        );
+        assert_eq!(
+            git_client.log(&top),
+            vec![
+                "Add the checker",
+                "Add the front end",
+                "Add the lexer",
+                "Initial commit"
+            ]
+        );
+        assert_eq!(
+            read_branch_metadata(&git_client, &branch)
+                .unwrap()
+                .unwrap()
+                .parent_branch_name(),
+            lexer
+        );
+        assert!(git_client.get_working_area().unwrap().is_empty());
+        assert_eq!(git_client.get_current_branch().unwrap(), branch);
+    }
+
+    #[test]
+    fn splitting_by_hunk_needs_a_terminal() {
+        let git_client = FakeGitClient::new("main");
+        let branch = git_client
+            .create_tracked_branch("Add the front end", &["lexer", "parser"])
+            .unwrap();
+        let revision = git_client.get_branch_revision(&branch).unwrap();
+
+        let error = split(&git_client, &options(SplitMode::Hunk, false)).unwrap_err();
+
+        assert!(error.to_string().contains("non-interactively"), "{error}");
+        assert_eq!(git_client.get_branch_revision(&branch).unwrap(), revision);
+    }
+}
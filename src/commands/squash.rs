@@ -0,0 +1,184 @@
+use color_eyre::Section;
+use eyre::{Ok, OptionExt, Result};
+use serde_json::json;
+
+use crate::{
+    cli::output,
+    commands::{
+        create::{commit, CommitOptions},
+        StackOptions,
+    },
+    git_client::{CommitStatus, GitClient},
+    stack::{operation::Operation, StackGraph},
+};
+
+/// Collapses the commits of the current branch into a single one, then restacks the branches
+/// stacked on top of it.
+///
+/// The commits made since the recorded parent revision are squashed, so a branch whose parent
+/// moved keeps only its own changes. Without a message, the editor opens with the messages of the
+/// squashed commits.
+///
+/// # Arguments
+///
+/// * `git_client` - The git client of the repository.
+/// * `options` - The message of the squashed commit, or whether it can be written in the editor.
+/// * `stack_options` - The trunk the stacks are rooted at.
+pub fn squash(
+    git_client: &dyn GitClient,
+    options: &CommitOptions,
+    stack_options: &StackOptions,
+) -> Result<()> {
+    Operation::ensure_none_in_progress(git_client)?;
+
+    let branch = git_client
+        .get_current_branch()
+        .ok_or_eyre("Failed to get the current branch")
+        .suggestion("Check out the branch to squash.")?;
+    let graph = StackGraph::load(git_client, stack_options.trunk.clone())?;
+    let Some(metadata) = graph.metadata(&branch) else {
+        return Err(eyre::eyre!("Branch {} is not tracked.", branch)).suggestion(format!(
+            "Run `gi track {branch}` to stack it on a parent first."
+        ));
+    };
+
+    if !git_client.get_working_area()?.is_empty() {
+        return Err(eyre::eyre!(
+            "Can't squash a branch with uncommitted changes."
+        ))
+        .suggestion("Commit the changes with `gi modify`, or stash them, and try again.");
+    }
+
+    let commits = git_client.list_commits(metadata.parent_branch_revision(), &branch)?;
+    if commits.len() < 2 {
+        return Err(eyre::eyre!("Branch {} has nothing to squash.", branch))
+            .suggestion("Reword its commit with `gi modify` instead.");
+    }
+
+    // The branch is snapshotted before its commits are rewritten, so that `gi abort` can restore it
+    let operation = Operation::begin(
+        git_client,
+        "squash",
+        std::slice::from_ref(&branch),
+        graph.descendants(&branch),
+    )?;
+
+    let mut messages = Vec::new();
+    for commit in &commits {
+        let title = git_client.get_commit_title(commit)?;
+        let body = git_client.get_commit_body(commit)?;
+        messages.push(if body.is_empty() {
+            title
+        } else {
+            format!("{title}\n\n{body}")
+        });
+    }
+
+    // Whatever happens to the commit, the branch must be left as it was unless it succeeds
+    let original_revision = git_client.get_branch_revision(&branch)?;
+    git_client.reset(metadata.parent_branch_revision())?;
+    git_client.add_all()?;
+    let commit_status = match options.message {
+        None if options.interactive => {
+            git_client.interactive_commit_with_message(&messages.join("\n\n"))
+        }
+        _ => commit(git_client, options),
+    };
+    if !matches!(commit_status.as_ref().ok(), Some(CommitStatus::Success)) {
+        git_client.reset(&original_revision)?;
+        operation.rollback(git_client)?;
+        commit_status?;
+
+        output::emit(json!({ "branch": branch, "aborted": true }));
+        return Ok(());
+    }
+
+    let restacked = operation.run(git_client)?;
+
+    output::print(format!("Squashed {} commits of {branch}.", commits.len()));
+    output::emit(json!({
+        "branch": branch,
+        "revision": git_client.get_branch_revision(&branch)?,
+        "squashed": commits.len(),
+        "restacked": restacked,
+    }));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{git_client::metadata::read_branch_metadata, testing::FakeGitClient};
+
+    fn options(message: Option<&str>, interactive: bool) -> CommitOptions {
+        CommitOptions {
+            all: false,
+            message: message.map(str::to_string),
+            interactive,
+        }
+    }
+
+    fn stack_options() -> StackOptions {
+        StackOptions {
+            trunk: "main".to_string(),
+        }
+    }
+
+    #[test]
+    fn squashing_collapses_the_commits_and_restacks_the_children() {
+        let git_client = FakeGitClient::new("main");
+        let lexer = git_client
+            .create_tracked_branch("Add the lexer", &["lexer"])
+            .unwrap();
+        git_client.commit("Fix the lexer", &["lexer"]).unwrap();
+        let parser = git_client
+            .create_tracked_branch("Add the parser", &["parser"])
+            .unwrap();
+        git_client.checkout(&lexer).unwrap();
+
+        git_client.queue_commit_message("Add the lexer", "With its fixes");
+        squash(&git_client, &options(None, true), &stack_options()).unwrap();
+
+        // The editor starts from the messages of every squashed commit, oldest first
+        assert_eq!(
+            git_client.prefilled_messages(),
+            vec!["Add the lexer\n\nFix the lexer"]
+        );
+        assert_eq!(
+            git_client.log(&lexer),
+            vec!["Add the lexer", "Initial commit"]
+        );
+        assert_eq!(
+            git_client.get_commit_body(&lexer).unwrap(),
+            "With its fixes"
+        );
+        assert_eq!(
+            git_client.log(&parser),
+            vec!["Add the parser", "Add the lexer", "Initial commit"]
+        );
+        assert_eq!(
+            read_branch_metadata(&git_client, &parser)
+                .unwrap()
+                .unwrap()
+                .parent_branch_revision(),
+            git_client.get_branch_revision(&lexer).unwrap()
+        );
+        assert_eq!(git_client.get_current_branch().unwrap(), lexer);
+    }
+
+    #[test]
+    fn aborting_the_message_leaves_the_branch_untouched() {
+        let git_client = FakeGitClient::new("main");
+        let lexer = git_client
+            .create_tracked_branch("Add the lexer", &["lexer"])
+            .unwrap();
+        git_client.commit("Fix the lexer", &["lexer"]).unwrap();
+        let revision = git_client.get_branch_revision(&lexer).unwrap();
+
+        squash(&git_client, &options(None, true), &stack_options()).unwrap();
+
+        assert_eq!(git_client.get_branch_revision(&lexer).unwrap(), revision);
+        assert!(git_client.get_working_area().unwrap().is_empty());
+    }
+}
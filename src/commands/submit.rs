@@ -1,28 +1,500 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read},
+};
+
 use color_eyre::Section;
-use eyre::{Ok, OptionExt, Result};
+use dialoguer::{theme::ColorfulTheme, Confirm};
+use eyre::{Context, Ok, OptionExt, Result};
+use serde_json::json;
 
 use crate::{
-    git_client,
-    git_provider::{provider_factory, GitProvider},
-    project::settings::get_project_settings,
+    cli::{interactive::is_interactive, output},
+    git_client::{metadata::create_branch_metadata, GitClient, RepositoryInfo},
+    git_provider::{
+        ask_for_pr_body, ask_for_pr_title, replace_stack_section, GitProvider, ProviderFactory,
+        PullRequest, StackEntry,
+    },
+    project::settings::{get_project_settings, project_config_path},
+    stack::{needs_restack, StackGraph},
 };
 
-pub async fn submit() -> Result<()> {
-    let git_client = git_client::get_git_client()?;
+/// How and where branches are submitted.
+pub struct SubmitOptions {
+    /// Whether to submit every branch from the bottom of the stack up to the current one.
+    pub stack: bool,
+    /// Whether to ask the user for titles and descriptions, instead of taking them from the commits.
+    pub interactive: bool,
+    /// The title of the pull request of the current branch, instead of asking for one.
+    pub title: Option<String>,
+    /// The description of the pull request of the current branch, instead of asking for one.
+    pub body: Option<String>,
+    pub trunk: String,
+    /// The remote branches are pushed to.
+    pub push_remote: String,
+    /// The remote of the repository pull requests are opened against.
+    pub target_remote: String,
+}
+
+impl SubmitOptions {
+    /// Creates submit options from the project settings, asking the user for the missing ones.
+    /// Titles and descriptions are only asked for when gi runs interactively.
+    pub fn from_project_settings(stack: bool) -> Result<Self> {
+        let mut settings = get_project_settings()?;
 
+        Ok(Self {
+            stack,
+            interactive: is_interactive(),
+            title: None,
+            body: None,
+            trunk: settings.get_trunk()?,
+            push_remote: settings.get_push_remote()?,
+            target_remote: settings.get_target_remote()?,
+        })
+    }
+}
+
+/// Pushes the current branch, or its whole stack, and opens or updates a pull request for every branch.
+///
+/// # Arguments
+///
+/// * `git_client` - The git client of the repository.
+/// * `provider_factory` - Creates the provider hosting the repository pull requests are opened against.
+/// * `options` - How and where the branches are submitted.
+pub async fn submit(
+    git_client: &dyn GitClient,
+    provider_factory: &ProviderFactory,
+    options: &SubmitOptions,
+) -> Result<()> {
     let branch = git_client
         .get_current_branch()
         .ok_or_eyre("Failed to get the current branch")
         .suggestion("Check whether you are checked out onto a branch")?;
-    let trunk = get_project_settings()?.get_trunk()?;
+    let graph = StackGraph::load(git_client, options.trunk.clone())?;
+
+    // Submitting a stack submits every branch from the bottom of the stack up to the current one
+    let mut branches = if options.stack {
+        graph.ancestors(&branch)
+    } else {
+        Vec::new()
+    };
+    branches.push(branch);
+
+    for branch in &branches {
+        let Some(metadata) = graph.metadata(branch) else {
+            continue;
+        };
+
+        if needs_restack(git_client, metadata) {
+            return Err(eyre::eyre!(
+                "Branch {} is not up to date with its parent {}.",
+                branch,
+                metadata.parent_branch_name()
+            ))
+            .suggestion("Run `gi restack` before submitting.");
+        }
+    }
+
+    let push_remote = options.push_remote.as_str();
+    let repository = git_client.get_repository_info(&options.target_remote)?;
+    let provider_obj = provider_factory(&repository)?;
+
+    // Branches pushed to a fork are opened as cross-repository pull requests
+    let push_repository = git_client.get_repository_info(push_remote)?;
+    let fork = (push_repository != repository).then_some(&push_repository);
+    if fork.is_some() && !provider_obj.supports_forks() {
+        return Err(eyre::eyre!(
+            "{} doesn't support opening pull requests from a fork.",
+            repository.provider
+        ))
+        .suggestion(format!(
+            "Set `pushRemote` to the same remote as `targetRemote` in {}.",
+            project_config_path(git_client)?.display()
+        ));
+    }
+
+    let current_branch = branches.last().cloned().unwrap_or_default();
+    let mut pull_requests = BTreeMap::new();
+    let mut submitted = Vec::new();
+    for branch in &branches {
+        // The title and description passed on the command line are meant for the current branch only
+        let (given_title, given_body) = if *branch == current_branch {
+            (options.title.clone(), options.body.clone())
+        } else {
+            (None, None)
+        };
+
+        // Stacked branches are reviewed against their parent, so that only their own commits show up
+        let base = graph.parent(branch).unwrap_or(graph.trunk());
+
+        output::print(format!("\nSubmitting {branch} against {base}:"));
+
+        let existing = provider_obj
+            .find_pull_request(&repository, fork, branch)
+            .await?;
+
+        let (pr, created) = match existing {
+            Some(existing) => {
+                let title = match given_title {
+                    Some(title) => title,
+                    None if options.interactive => ask_for_pr_title(&existing.title)?,
+                    None => existing.title,
+                };
+                let body = match given_body {
+                    Some(body) => body,
+                    None if options.interactive
+                        && Confirm::with_theme(&ColorfulTheme::default())
+                            .with_prompt("Update the PR description?")
+                            .default(false)
+                            .interact()? =>
+                    {
+                        ask_for_pr_body(&git_client.get_commit_body(branch)?)?
+                    }
+                    None => existing.body,
+                };
+
+                git_client.push_branch(push_remote, branch, true)?;
+                let pr = provider_obj
+                    .update_pull_request(&repository, existing.number, base, &title, &body)
+                    .await?;
+
+                output::print(format!(
+                    "\nPull request updated successfully! You can check it out at:\n{}",
+                    pr.url
+                ));
+                (pr, false)
+            }
+            None => {
+                let title = match given_title {
+                    Some(title) => title,
+                    None if options.interactive => {
+                        ask_for_pr_title(&git_client.get_commit_title(branch)?)?
+                    }
+                    None => git_client.get_commit_title(branch)?,
+                };
+                let body = match given_body {
+                    Some(body) => body,
+                    None if options.interactive => {
+                        ask_for_pr_body(&git_client.get_commit_body(branch)?)?
+                    }
+                    None => git_client.get_commit_body(branch)?,
+                };
+
+                git_client.push_branch(push_remote, branch, false)?;
+                let pr = provider_obj
+                    .create_pull_request(&repository, fork, branch, base, &title, &body)
+                    .await?;
+
+                output::print(format!(
+                    "\nPull request created successfully! You can check it out at:\n{}",
+                    pr.url
+                ));
+                (pr, true)
+            }
+        };
+
+        if let Some(mut metadata) = graph.metadata(branch).cloned() {
+            if metadata.pr_number() != Some(pr.number) {
+                metadata.set_pr_number(Some(pr.number));
+                create_branch_metadata(git_client, branch.to_string(), &metadata)?;
+            }
+        }
+
+        submitted.push(json!({
+            "branch": branch,
+            "base": base,
+            "number": pr.number,
+            "url": pr.url,
+            "created": created,
+        }));
+        pull_requests.insert(branch.clone(), pr);
+    }
+
+    let graph = StackGraph::load(git_client, graph.trunk().to_string())?;
+    update_stack_sections(
+        provider_obj.as_ref(),
+        &graph,
+        &repository,
+        &current_branch,
+        pull_requests,
+    )
+    .await?;
+
+    output::emit(json!({ "pullRequests": submitted }));
+
+    Ok(())
+}
+
+/// Reads the description of a pull request from a file, or from stdin when the path is `-`.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file holding the description.
+///
+/// # Returns
+///
+/// A `Result` containing the description, or an error if it can't be read.
+pub fn read_body_file(path: &str) -> Result<String> {
+    if path == "-" {
+        let mut body = String::new();
+        io::stdin()
+            .read_to_string(&mut body)
+            .context("Failed to read the PR description from stdin")?;
+
+        return Ok(body);
+    }
+
+    fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the PR description from {path}"))
+        .suggestion("Check that the file exists and that you have read permissions to it.")
+}
 
-    let (provider, owner, repo) = git_client.get_repository_info()?;
-    let provider_obj = provider_factory(&provider)?;
+/// Refreshes the stack section in the description of every pull request of the stack the branch belongs to.
+///
+/// # Arguments
+///
+/// * `pull_requests` - The pull requests already known, by branch name. Pull requests of the other
+///   branches in the stack are fetched using the numbers recorded in their metadata.
+async fn update_stack_sections(
+    provider: &dyn GitProvider,
+    graph: &StackGraph,
+    repository: &RepositoryInfo,
+    branch: &str,
+    mut pull_requests: BTreeMap<String, PullRequest>,
+) -> Result<()> {
+    let ancestors = graph.ancestors(branch);
+    let bottom = ancestors.first().map(String::as_str).unwrap_or(branch);
+    let base = graph.parent(bottom).unwrap_or(graph.trunk()).to_string();
+    let bottom_depth = graph.ancestors(bottom).len();
 
-    git_client.push_branch(&branch)?;
-    provider_obj
-        .create_pull_request(&owner, &repo, &branch, &trunk)
-        .await?;
+    let stack = std::iter::once(bottom.to_string())
+        .chain(graph.descendants(bottom))
+        .collect::<Vec<String>>();
+
+    let mut entries = Vec::new();
+    for stacked_branch in &stack {
+        let pull_request = match pull_requests.remove(stacked_branch) {
+            Some(pull_request) => pull_request,
+            None => match graph.metadata(stacked_branch).and_then(|m| m.pr_number()) {
+                Some(number) => provider.get_pull_request(repository, number).await?,
+                None => continue,
+            },
+        };
+
+        entries.push(StackEntry {
+            depth: graph.ancestors(stacked_branch).len() - bottom_depth,
+            pull_request,
+        });
+    }
+
+    for entry in &entries {
+        let pr = &entry.pull_request;
+        let section = provider.render_stack_section(&base, &entries, pr.number);
+        let body = replace_stack_section(&pr.body, &section);
+
+        if body != pr.body {
+            provider
+                .update_pull_request(repository, pr.number, &pr.base, &pr.title, &body)
+                .await?;
+        }
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        git_client::metadata::read_branch_metadata,
+        git_provider::STACK_SECTION_START,
+        testing::{github_repository, FakeGitClient, ProviderCall, Push, RecordingProvider},
+    };
+
+    fn options(stack: bool) -> SubmitOptions {
+        SubmitOptions {
+            stack,
+            interactive: false,
+            title: None,
+            body: None,
+            trunk: "main".to_string(),
+            push_remote: "origin".to_string(),
+            target_remote: "origin".to_string(),
+        }
+    }
+
+    /// Creates a stacked branch with a single commit on top of the current branch.
+    fn create_branch(git_client: &FakeGitClient, title: &str, body: &str) -> String {
+        git_client
+            .create_tracked_branch(&format!("{title}\n\n{body}"), &[title])
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn submit_opens_a_pull_request_for_a_created_branch() {
+        let git_client = FakeGitClient::new("main");
+        git_client.add_remote("origin", github_repository("owner", "repo"));
+        let provider = RecordingProvider::new();
+
+        let branch = create_branch(&git_client, "Add the parser", "It parses.");
+        submit(&git_client, &provider.factory(), &options(false))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            git_client.pushes(),
+            vec![Push {
+                remote: "origin".to_string(),
+                branch: branch.clone(),
+                revision: git_client.get_branch_revision(&branch).unwrap(),
+                force: false,
+            }]
+        );
+
+        let calls = provider.calls();
+        assert_eq!(
+            calls[..2],
+            [
+                ProviderCall::Find {
+                    fork_owner: None,
+                    branch: branch.clone(),
+                },
+                ProviderCall::Create {
+                    fork_owner: None,
+                    branch: branch.clone(),
+                    base: "main".to_string(),
+                    title: "Add the parser".to_string(),
+                    body: "It parses.".to_string(),
+                },
+            ]
+        );
+
+        let pr = provider.pull_request_for(&branch).unwrap();
+        assert_eq!(pr.url, "https://github.com/owner/repo/pull/1");
+        assert!(pr.body.starts_with("It parses."));
+        assert!(pr.body.contains(STACK_SECTION_START));
+
+        let metadata = read_branch_metadata(&git_client, &branch).unwrap().unwrap();
+        assert_eq!(metadata.pr_number(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn submit_stack_opens_pull_requests_against_parents() {
+        let git_client = FakeGitClient::new("main");
+        git_client.add_remote("origin", github_repository("owner", "repo"));
+        let provider = RecordingProvider::new();
+
+        let bottom = create_branch(&git_client, "Add the lexer", "");
+        let top = create_branch(&git_client, "Add the parser", "");
+        submit(&git_client, &provider.factory(), &options(true))
+            .await
+            .unwrap();
+
+        let bottom_pr = provider.pull_request_for(&bottom).unwrap();
+        let top_pr = provider.pull_request_for(&top).unwrap();
+        assert_eq!(bottom_pr.base, "main");
+        assert_eq!(top_pr.base, bottom);
+
+        // Every pull request links to the whole stack
+        for pr in [&bottom_pr, &top_pr] {
+            assert!(pr.body.contains("#1"), "{}", pr.body);
+            assert!(pr.body.contains("#2"), "{}", pr.body);
+        }
+    }
+
+    #[tokio::test]
+    async fn given_title_and_description_only_apply_to_the_current_branch() {
+        let git_client = FakeGitClient::new("main");
+        git_client.add_remote("origin", github_repository("owner", "repo"));
+        let provider = RecordingProvider::new();
+
+        let bottom = create_branch(&git_client, "Add the lexer", "");
+        let top = create_branch(&git_client, "Add the parser", "");
+        let options = SubmitOptions {
+            title: Some("Parser".to_string()),
+            body: Some("Parses tokens.".to_string()),
+            ..options(true)
+        };
+        submit(&git_client, &provider.factory(), &options)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            provider.pull_request_for(&bottom).unwrap().title,
+            "Add the lexer"
+        );
+        let top_pr = provider.pull_request_for(&top).unwrap();
+        assert_eq!(top_pr.title, "Parser");
+        assert!(top_pr.body.starts_with("Parses tokens."));
+    }
+
+    #[tokio::test]
+    async fn resubmitting_updates_the_existing_pull_request() {
+        let git_client = FakeGitClient::new("main");
+        git_client.add_remote("origin", github_repository("owner", "repo"));
+        let provider = RecordingProvider::new();
+
+        let branch = create_branch(&git_client, "Add the parser", "");
+        submit(&git_client, &provider.factory(), &options(false))
+            .await
+            .unwrap();
+        submit(&git_client, &provider.factory(), &options(false))
+            .await
+            .unwrap();
+
+        assert_eq!(provider.pull_requests().len(), 1);
+        assert!(git_client.pushes()[1].force);
+        assert!(provider.calls().contains(&ProviderCall::Update {
+            number: 1,
+            base: "main".to_string(),
+            title: "Add the parser".to_string(),
+            body: provider.pull_request_for(&branch).unwrap().body,
+        }));
+    }
+
+    #[tokio::test]
+    async fn submit_from_a_fork_sets_the_fork_owner() {
+        let git_client = FakeGitClient::new("main");
+        git_client.add_remote("origin", github_repository("me", "repo"));
+        git_client.add_remote("upstream", github_repository("owner", "repo"));
+        let provider = RecordingProvider::new();
+
+        let branch = create_branch(&git_client, "Add the parser", "");
+        let options = SubmitOptions {
+            target_remote: "upstream".to_string(),
+            ..options(false)
+        };
+        submit(&git_client, &provider.factory(), &options)
+            .await
+            .unwrap();
+
+        assert_eq!(git_client.pushes()[0].remote, "origin");
+        assert!(provider.calls().contains(&ProviderCall::Create {
+            fork_owner: Some("me".to_string()),
+            branch,
+            base: "main".to_string(),
+            title: "Add the parser".to_string(),
+            body: String::new(),
+        }));
+    }
+
+    #[tokio::test]
+    async fn submit_refuses_branches_that_need_restacking() {
+        let git_client = FakeGitClient::new("main");
+        git_client.add_remote("origin", github_repository("owner", "repo"));
+        let provider = RecordingProvider::new();
+
+        create_branch(&git_client, "Add the parser", "");
+        git_client.checkout("main").unwrap();
+        git_client.commit("Fix the build", &["build.rs"]).unwrap();
+        let branch = git_client.branches()[0].clone();
+        git_client.checkout(&branch).unwrap();
+
+        let result = submit(&git_client, &provider.factory(), &options(false)).await;
+
+        assert!(result.is_err());
+        assert!(provider.calls().is_empty());
+        assert!(git_client.pushes().is_empty());
+    }
+}
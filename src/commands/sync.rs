@@ -0,0 +1,258 @@
+use color_eyre::Section;
+use dialoguer::{theme::ColorfulTheme, Confirm};
+use eyre::{Ok, Result};
+use serde_json::json;
+
+use crate::{
+    cli::{interactive::is_interactive, output},
+    git_client::{
+        metadata::{delete_branch_metadata, read_branch_metadata},
+        GitClient,
+    },
+    git_provider::{ProviderFactory, PullRequest, PullRequestState},
+    project::settings::get_project_settings,
+    stack::{operation::Operation, reparent_children, StackGraph},
+};
+
+/// Where the trunk is synced from, and how finished branches are pruned.
+pub struct SyncOptions {
+    /// Whether to delete the branches of merged and closed pull requests without asking.
+    pub force: bool,
+    /// Whether to ask the user before deleting each branch.
+    pub interactive: bool,
+    pub trunk: String,
+    /// The remote the trunk is fetched from, which pull requests are opened against.
+    pub remote: String,
+}
+
+impl SyncOptions {
+    /// Creates sync options from the project settings, asking the user for the missing ones.
+    pub fn from_project_settings(force: bool) -> Result<Self> {
+        let mut settings = get_project_settings()?;
+
+        Ok(Self {
+            force,
+            interactive: is_interactive(),
+            trunk: settings.get_trunk()?,
+            remote: settings.get_target_remote()?,
+        })
+    }
+}
+
+/// Brings the trunk up to date, deletes the branches whose pull requests were merged or closed and
+/// restacks the remaining branches.
+///
+/// The children of a deleted branch are stacked on its parent, so that a stack whose bottom branch
+/// was merged ends up on the trunk.
+///
+/// # Arguments
+///
+/// * `git_client` - The git client of the repository.
+/// * `provider_factory` - Creates the provider hosting the pull requests of the tracked branches.
+/// * `options` - Where the trunk is synced from, and whether to ask before deleting branches.
+pub async fn sync(
+    git_client: &dyn GitClient,
+    provider_factory: &ProviderFactory,
+    options: &SyncOptions,
+) -> Result<()> {
+    Operation::ensure_none_in_progress(git_client)?;
+
+    let trunk = options.trunk.as_str();
+    git_client.fetch_branch(&options.remote, trunk)?;
+    output::print(format!("Synced {trunk} with {}.", options.remote));
+
+    let graph = StackGraph::load(git_client, trunk.to_string())?;
+    let repository = git_client.get_repository_info(&options.remote)?;
+    let provider = provider_factory(&repository)?;
+
+    // Only branches submitted through gi know the number of their pull request
+    let mut finished = Vec::new();
+    for branch in graph.topological_order() {
+        let Some(number) = graph.metadata(&branch).and_then(|m| m.pr_number()) else {
+            continue;
+        };
+
+        let pull_request = provider.get_pull_request(&repository, number).await?;
+        if pull_request.state != PullRequestState::Open
+            && confirm_deletion(&branch, &pull_request, options)?
+        {
+            finished.push((branch, pull_request));
+        }
+    }
+
+    // Deleted branches can't stay checked out
+    if let Some(current_branch) = git_client.get_current_branch() {
+        if finished.iter().any(|(branch, _)| *branch == current_branch) {
+            git_client.checkout(trunk)?;
+        }
+    }
+
+    // Every tracked branch is snapshotted, so that `gi abort` also restores the deleted ones
+    let operation = Operation::begin(git_client, "sync", &[], graph.topological_order())?;
+
+    let mut deleted = Vec::new();
+    for (branch, pull_request) in &finished {
+        // Parents are deleted first, so the metadata is read again to see where they left the branch
+        let parent = match read_branch_metadata(git_client, branch)? {
+            Some(metadata) => metadata.parent_branch_name().to_string(),
+            None => trunk.to_string(),
+        };
+
+        reparent_children(git_client, branch, &parent)?;
+        delete_branch_metadata(git_client, branch)?;
+        git_client.delete_branch(branch)?;
+
+        output::print(format!(
+            "Deleted {branch}, its pull request #{} was {}.",
+            pull_request.number, pull_request.state
+        ));
+        deleted.push(json!({
+            "branch": branch,
+            "number": pull_request.number,
+            "state": pull_request.state,
+        }));
+    }
+
+    let restacked = operation.run(git_client)?;
+    output::emit(json!({
+        "trunk": trunk,
+        "revision": git_client.get_branch_revision(trunk)?,
+        "deleted": deleted,
+        "restacked": restacked,
+    }));
+
+    Ok(())
+}
+
+/// Asks the user whether to delete the branch of a merged or closed pull request.
+///
+/// # Returns
+///
+/// A `Result` containing whether to delete the branch, or an error if the user can't be asked.
+fn confirm_deletion(
+    branch: &str,
+    pull_request: &PullRequest,
+    options: &SyncOptions,
+) -> Result<bool> {
+    if options.force {
+        return Ok(true);
+    }
+
+    if !options.interactive {
+        return Err(eyre::eyre!(
+            "Can't ask whether to delete {branch} when running non-interactively."
+        ))
+        .suggestion("Pass `--force` to delete the branches of merged and closed pull requests.");
+    }
+
+    // Closed pull requests may hold work worth keeping, unlike merged ones
+    let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "The pull request of {branch} (#{}) was {}. Delete the branch?",
+            pull_request.number, pull_request.state
+        ))
+        .default(pull_request.state == PullRequestState::Merged)
+        .interact()?;
+
+    Ok(confirmed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        commands::submit::{submit, SubmitOptions},
+        testing::{github_repository, FakeGitClient, RecordingProvider},
+    };
+
+    fn options(force: bool) -> SyncOptions {
+        SyncOptions {
+            force,
+            interactive: false,
+            trunk: "main".to_string(),
+            remote: "origin".to_string(),
+        }
+    }
+
+    /// Creates a stack of two branches and opens their pull requests, returning the branches bottom first.
+    async fn submitted_stack(
+        git_client: &FakeGitClient,
+        provider: &RecordingProvider,
+    ) -> [String; 2] {
+        git_client.add_remote("origin", github_repository("owner", "repo"));
+
+        let branches = ["Add the lexer", "Add the parser"]
+            .map(|title| git_client.create_tracked_branch(title, &[title]).unwrap());
+
+        let options = SubmitOptions {
+            stack: true,
+            interactive: false,
+            title: None,
+            body: None,
+            trunk: "main".to_string(),
+            push_remote: "origin".to_string(),
+            target_remote: "origin".to_string(),
+        };
+        submit(git_client, &provider.factory(), &options)
+            .await
+            .unwrap();
+
+        branches
+    }
+
+    #[tokio::test]
+    async fn sync_deletes_merged_branches_and_restacks_their_children() {
+        let git_client = FakeGitClient::new("main");
+        let provider = RecordingProvider::new();
+        let [bottom, top] = submitted_stack(&git_client, &provider).await;
+
+        // The bottom branch was squash merged into the remote trunk
+        git_client.checkout("main").unwrap();
+        git_client.create_branch("merge").unwrap();
+        git_client.checkout("merge").unwrap();
+        git_client.commit("Add the lexer (#1)", &["lexer"]).unwrap();
+        let merged = git_client.get_branch_revision("merge").unwrap();
+        git_client.set_remote_branch("origin", "main", &merged);
+        git_client.checkout(&bottom).unwrap();
+        git_client.delete_branch("merge").unwrap();
+        provider.set_pull_request_state(1, PullRequestState::Merged);
+
+        sync(&git_client, &provider.factory(), &options(true))
+            .await
+            .unwrap();
+
+        assert_eq!(git_client.get_branch_revision("main").unwrap(), merged);
+        assert_eq!(git_client.branches(), vec![top.clone(), "main".into()]);
+        assert!(read_branch_metadata(&git_client, &bottom)
+            .unwrap()
+            .is_none());
+        assert_eq!(git_client.get_current_branch().as_deref(), Some("main"));
+
+        let metadata = read_branch_metadata(&git_client, &top).unwrap().unwrap();
+        assert_eq!(metadata.parent_branch_name(), "main");
+        assert_eq!(metadata.parent_branch_revision(), merged);
+        assert_eq!(
+            git_client.log(&top),
+            vec!["Add the parser", "Add the lexer (#1)", "Initial commit"]
+        );
+    }
+
+    #[tokio::test]
+    async fn sync_needs_force_to_delete_branches_when_non_interactive() {
+        let git_client = FakeGitClient::new("main");
+        let provider = RecordingProvider::new();
+        let [bottom, top] = submitted_stack(&git_client, &provider).await;
+        provider.set_pull_request_state(2, PullRequestState::Closed);
+
+        let error = sync(&git_client, &provider.factory(), &options(false))
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().contains(&top), "{error}");
+        assert_eq!(
+            git_client.branches(),
+            vec![bottom, top.clone(), "main".into()]
+        );
+        assert!(read_branch_metadata(&git_client, &top).unwrap().is_some());
+    }
+}
@@ -0,0 +1,263 @@
+use color_eyre::Section;
+use dialoguer::{theme::ColorfulTheme, FuzzySelect};
+use eyre::{Ok, OptionExt, Result};
+use serde_json::json;
+
+use crate::{
+    cli::{interactive::ensure_interactive, output},
+    commands::StackOptions,
+    git_client::{
+        metadata::{create_branch_metadata, delete_branch_metadata, BranchMetadata},
+        GitClient,
+    },
+    stack::StackGraph,
+};
+
+/// Starts tracking a branch made outside of gi, stacking it on top of a parent.
+///
+/// The branch is recorded as stacked on the merge base with its parent, so that restacking it
+/// replays only the commits made since it diverged from the parent.
+///
+/// # Arguments
+///
+/// * `git_client` - The git client of the repository.
+/// * `branch` - The branch to track, or `None` for the current one.
+/// * `parent` - The parent of the branch, or `None` to pick it among the most likely ones.
+/// * `options` - The trunk the stacks are rooted at.
+pub fn track(
+    git_client: &dyn GitClient,
+    branch: Option<&str>,
+    parent: Option<&str>,
+    options: &StackOptions,
+) -> Result<()> {
+    let branch = match branch {
+        Some(branch) => branch.to_string(),
+        None => git_client
+            .get_current_branch()
+            .ok_or_eyre("Failed to get the current branch")
+            .suggestion("Pass the name of the branch to track.")?,
+    };
+    ensure_branch_exists(git_client, &branch)?;
+
+    let graph = StackGraph::load(git_client, options.trunk.clone())?;
+    if branch == graph.trunk() {
+        return Err(eyre::eyre!("The trunk can't be stacked on another branch."))
+            .suggestion("Track the branches stacked on top of the trunk instead.");
+    }
+    if graph.is_tracked(&branch) {
+        return Err(eyre::eyre!("Branch {} is already tracked.", branch)).suggestion(format!(
+            "Run `gi untrack {branch}` first to track it on another parent."
+        ));
+    }
+
+    let parent = match parent {
+        Some(parent) => {
+            ensure_branch_exists(git_client, parent)?;
+            if parent == branch || graph.descendants(&branch).iter().any(|d| d == parent) {
+                return Err(eyre::eyre!(
+                    "Branch {} can't be stacked on {}, which is stacked on top of it.",
+                    branch,
+                    parent
+                ))
+                .suggestion("Pick a parent below the branch.");
+            }
+
+            parent.to_string()
+        }
+        None => select_parent(git_client, &graph, &branch)?,
+    };
+
+    let merge_base = git_client.merge_base(&parent, &branch)?;
+    let metadata = BranchMetadata::new(parent.clone(), merge_base);
+    create_branch_metadata(git_client, branch.clone(), &metadata)?;
+
+    output::print(format!("Tracking {branch} on top of {parent}."));
+    output::emit(json!({ "branch": branch, "metadata": metadata }));
+
+    Ok(())
+}
+
+/// Stops tracking a branch, leaving the branch itself untouched.
+///
+/// The branches stacked on top of it stay tracked, and become the bottom of their own stack.
+///
+/// # Arguments
+///
+/// * `git_client` - The git client of the repository.
+/// * `branch` - The branch to stop tracking, or `None` for the current one.
+/// * `options` - The trunk the stacks are rooted at.
+pub fn untrack(
+    git_client: &dyn GitClient,
+    branch: Option<&str>,
+    options: &StackOptions,
+) -> Result<()> {
+    let branch = match branch {
+        Some(branch) => branch.to_string(),
+        None => git_client
+            .get_current_branch()
+            .ok_or_eyre("Failed to get the current branch")
+            .suggestion("Pass the name of the branch to untrack.")?,
+    };
+
+    let graph = StackGraph::load(git_client, options.trunk.clone())?;
+    if !graph.is_tracked(&branch) {
+        return Err(eyre::eyre!("Branch {} is not tracked.", branch))
+            .suggestion("Run `gi log` to list the tracked branches.");
+    }
+
+    delete_branch_metadata(git_client, &branch)?;
+
+    output::print(format!("Stopped tracking {branch}."));
+    output::emit(json!({ "branch": branch, "untracked": true }));
+
+    Ok(())
+}
+
+/// Ranks the branches the specified one may have been created from, most likely first.
+///
+/// The candidates are the trunk and every tracked branch that isn't stacked on top of the branch.
+/// The closer the merge base of a candidate is to the branch, the more likely the candidate is its
+/// parent; among candidates sharing a merge base, the ones whose tip is the merge base come first.
+///
+/// # Returns
+///
+/// A `Result` containing the candidates, or an error if none of them shares history with the branch.
+pub fn rank_parents(
+    git_client: &dyn GitClient,
+    graph: &StackGraph,
+    branch: &str,
+) -> Result<Vec<String>> {
+    let descendants = graph.descendants(branch);
+    let mut candidates = Vec::new();
+    for candidate in std::iter::once(graph.trunk().to_string()).chain(graph.topological_order()) {
+        if candidate == branch || descendants.contains(&candidate) {
+            continue;
+        }
+
+        // Branches without common history with the branch can't be its parent
+        let Result::Ok(merge_base) = git_client.merge_base(&candidate, branch) else {
+            continue;
+        };
+        let tip = git_client.get_branch_revision(&candidate)?;
+        candidates.push((candidate, merge_base, tip));
+    }
+
+    // The merge base that every other one is an ancestor of is the closest to the branch
+    let mut ranked = Vec::new();
+    for (candidate, merge_base, tip) in &candidates {
+        let mut depth = 0;
+        for (_, other, _) in &candidates {
+            if git_client.is_ancestor(other, merge_base)? {
+                depth += 1;
+            }
+        }
+
+        ranked.push((depth, tip == merge_base, candidate.clone()));
+    }
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+
+    if ranked.is_empty() {
+        return Err(eyre::eyre!(
+            "Branch {} shares no history with the trunk or the tracked branches.",
+            branch
+        ))
+        .suggestion("Pass the parent of the branch with `--parent`.");
+    }
+
+    Ok(ranked
+        .into_iter()
+        .map(|(_, _, candidate)| candidate)
+        .collect())
+}
+
+/// Asks the user to confirm the most likely parent of a branch, or to pick another one.
+fn select_parent(git_client: &dyn GitClient, graph: &StackGraph, branch: &str) -> Result<String> {
+    let candidates = rank_parents(git_client, graph, branch)?;
+
+    ensure_interactive(
+        &format!("to know which branch {branch} is stacked on"),
+        &format!(
+            "Pass the parent with `--parent`, e.g. `gi track {branch} --parent {}`.",
+            candidates[0]
+        ),
+    )?;
+
+    let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Which branch is {branch} stacked on?"))
+        .default(0)
+        .items(&candidates)
+        .interact()?;
+
+    Ok(candidates[selection].clone())
+}
+
+/// Fails with a suggestion to list the branches if the specified one doesn't exist.
+pub(crate) fn ensure_branch_exists(git_client: &dyn GitClient, branch: &str) -> Result<()> {
+    git_client
+        .get_branch_revision(&format!("refs/heads/{branch}"))
+        .map(|_| ())
+        .map_err(|_| eyre::eyre!("Branch {} doesn't exist.", branch))
+        .suggestion("Run `git branch` to list the branches of the repository.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeGitClient;
+
+    fn create_untracked(git_client: &FakeGitClient, branch: &str, title: &str) {
+        git_client.create_branch(branch).unwrap();
+        git_client.checkout(branch).unwrap();
+        git_client.commit(title, &[title]).unwrap();
+    }
+
+    #[test]
+    fn the_closest_branch_is_the_most_likely_parent() {
+        let git_client = FakeGitClient::new("main");
+        let lexer = git_client
+            .create_tracked_branch("Add the lexer", &["lexer"])
+            .unwrap();
+        let parser = git_client
+            .create_tracked_branch("Add the parser", &["parser"])
+            .unwrap();
+        create_untracked(&git_client, "checker", "Add the checker");
+
+        let graph = StackGraph::load(&git_client, "main".to_string()).unwrap();
+        let candidates = rank_parents(&git_client, &graph, "checker").unwrap();
+
+        assert_eq!(candidates, vec![parser, lexer, "main".to_string()]);
+    }
+
+    #[test]
+    fn branches_stacked_on_top_are_not_candidates() {
+        let git_client = FakeGitClient::new("main");
+        create_untracked(&git_client, "lexer", "Add the lexer");
+        let parser = git_client
+            .create_tracked_branch("Add the parser", &["parser"])
+            .unwrap();
+
+        let graph = StackGraph::load(&git_client, "main".to_string()).unwrap();
+        let candidates = rank_parents(&git_client, &graph, "lexer").unwrap();
+
+        assert_eq!(graph.parent(&parser), Some("lexer"));
+        assert_eq!(candidates, vec!["main".to_string()]);
+    }
+
+    #[test]
+    fn a_branch_is_stacked_on_its_merge_base_with_the_parent() {
+        let git_client = FakeGitClient::new("main");
+        let base = git_client.get_branch_revision("main").unwrap();
+        create_untracked(&git_client, "lexer", "Add the lexer");
+        git_client.checkout("main").unwrap();
+        git_client.commit("Fix the build", &["build"]).unwrap();
+
+        let graph = StackGraph::load(&git_client, "main".to_string()).unwrap();
+        let parent = rank_parents(&git_client, &graph, "lexer")
+            .unwrap()
+            .remove(0);
+        let merge_base = git_client.merge_base(&parent, "lexer").unwrap();
+
+        assert_eq!(parent, "main");
+        assert_eq!(merge_base, base);
+    }
+}
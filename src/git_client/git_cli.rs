@@ -1,9 +1,12 @@
-use super::{CommitStatus, GitClient};
-use crate::git_provider::{get_provider_enum, SupportedProviders};
+use super::{remote_url::parse_remote_url, CommitStatus, GitClient, RebaseStatus, RepositoryInfo};
+use crate::{cli::output, user::settings::get_user_settings};
 use color_eyre::Section;
-use eyre::{Context, ContextCompat, OptionExt, Result};
-use regex::Regex;
-use std::{io::Write, process::Command};
+use eyre::{Context, OptionExt, Result};
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
 
 pub struct GitCli {}
 
@@ -20,12 +23,54 @@ impl GitCli {
     }
 }
 
+/// Returns where the summary git prints after committing goes, keeping stdout for the JSON output.
+fn commit_output() -> Stdio {
+    if output::is_json() {
+        std::io::stderr().into()
+    } else {
+        Stdio::inherit()
+    }
+}
+
+/// Runs `git rev-parse` with a flag printing a path, and returns the path made absolute.
+fn rev_parse_path(flag: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", flag])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // Some paths, like the common git directory, are printed relative to the current directory
+    let path = String::from_utf8(output.stdout).ok()?;
+    let path = std::env::current_dir().ok()?.join(Path::new(path.trim()));
+
+    path.to_str().map(str::to_string)
+}
+
 /// Implementation of the `GitClient` trait for the `GitCli` struct.
 impl GitClient for GitCli {
     fn interactive_commit(&self) -> Result<CommitStatus> {
         // Executes the `git commit -s` command to perform an interactive commit.
         let status = Command::new("git")
             .args(["commit", "-s"])
+            .stdout(commit_output())
+            .status()
+            .context("Interactive commit failed")?;
+
+        if !status.success() {
+            return Ok(CommitStatus::Aborted);
+        }
+
+        Ok(CommitStatus::Success)
+    }
+
+    fn interactive_commit_with_message(&self, message: &str) -> Result<CommitStatus> {
+        // Executes the `git commit -s -e -m <message>` command to edit the message before committing.
+        let status = Command::new("git")
+            .args(["commit", "-s", "-e", "-m", message])
+            .stdout(commit_output())
             .status()
             .context("Interactive commit failed")?;
 
@@ -36,6 +81,59 @@ impl GitClient for GitCli {
         Ok(CommitStatus::Success)
     }
 
+    fn interactive_amend(&self) -> Result<CommitStatus> {
+        // Executes the `git commit --amend -s` command to perform an interactive amend of the current commit.
+        let status = Command::new("git")
+            .args(["commit", "--amend", "-s"])
+            .stdout(commit_output())
+            .status()
+            .context("Interactive amend failed")?;
+
+        if !status.success() {
+            return Ok(CommitStatus::Aborted);
+        }
+
+        Ok(CommitStatus::Success)
+    }
+
+    fn commit_with_message(&self, message: &str) -> Result<()> {
+        // Executes the `git commit -s -m <message>` command to commit without opening an editor.
+        let output = Command::new("git")
+            .args(["commit", "-s", "-m", message])
+            .output()
+            .context("Failed to commit")?;
+
+        if !output.status.success() {
+            return Err(eyre::eyre!(
+                "Failed to commit: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn amend_with_message(&self, message: Option<&str>) -> Result<()> {
+        // Executes the `git commit --amend -s` command, keeping the current message unless a new one is given.
+        let mut command = Command::new("git");
+        command.args(["commit", "--amend", "-s"]);
+        match message {
+            Some(message) => command.args(["-m", message]),
+            None => command.arg("--no-edit"),
+        };
+
+        let output = command.output().context("Failed to amend the commit")?;
+
+        if !output.status.success() {
+            return Err(eyre::eyre!(
+                "Failed to amend the commit: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(())
+    }
+
     fn checkout(&self, branch: &str) -> Result<()> {
         // Executes the `git checkout <branch>` command to check out the specified branch.
         let output = Command::new("git")
@@ -70,58 +168,51 @@ impl GitClient for GitCli {
         }
     }
 
-    fn get_repository_info(&self) -> Result<(SupportedProviders, String, String)> {
-        // Executes the `git config --get remote.origin.url` command to get the URL of the remote repository.
-        let out = Command::new("git")
-            .args(["config", "--get", "remote.origin.url"])
+    fn get_repository_info(&self, remote: &str) -> Result<RepositoryInfo> {
+        // Executes the `git config --get remote.<remote>.url` command to get the URL of the remote repository.
+        let output = Command::new("git")
+            .args(["config", "--get", &format!("remote.{remote}.url")])
             .output()
             .context("Failed to get git remote")
-            .suggestion("Make sure you are inside a git repository")?
-            .stdout
-            .iter()
-            .map(|&c| c as char)
-            .collect::<String>();
+            .suggestion("Make sure you are inside a git repository")?;
 
-        let is_https = out.starts_with("https");
+        if !output.status.success() {
+            return Err(eyre::eyre!("Remote {remote} doesn't exist"))
+                .suggestion("Run `git remote -v` to list the remotes of the repository");
+        }
 
-        // Parses the remote repository URL to extract the provider, owner, and repository name.
-        let regex = if is_https {
-            Regex::new(r"https://(?P<provider>.+)/(?P<owner>.+)/(?P<repo>.+).git").unwrap()
-        } else {
-            Regex::new(r"@(?P<provider>.+):(?P<owner>.+)/(?P<repo>.+).git").unwrap()
-        };
+        let url = String::from_utf8(output.stdout).context("Failed to parse git remote URL")?;
 
-        let captures = regex
-            .captures(&out)
-            .context("Unrecognised git repository URL format")
-            .suggestion("Use a HTTPS or SSH git repository URL")?;
+        parse_remote_url(&url)?.repository_info(&*get_user_settings()?)
+    }
+
+    fn list_remotes(&self) -> Result<Vec<String>> {
+        // Executes the `git remote` command to list the names of the remotes.
+        let output = Command::new("git")
+            .arg("remote")
+            .output()
+            .context("Failed to list remotes")?;
 
-        let provider = captures.name("provider").unwrap().as_str();
-        let owner = captures.name("owner").unwrap().as_str().to_string();
-        let repo = captures.name("repo").unwrap().as_str().to_string();
-        let provider_enum = get_provider_enum(provider)?;
+        if !output.status.success() {
+            return Err(eyre::eyre!("Failed to list remotes"));
+        }
 
-        Ok((provider_enum, owner, repo))
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect())
     }
 
     fn get_repository_root(&self) -> Option<String> {
-        // Executes the `git rev-parse --show-toplevel` command to get the root directory of the current Git repository.
-        let root = Command::new("git")
-            .args(["rev-parse", "--show-toplevel"])
-            .output();
+        rev_parse_path("--show-toplevel")
+    }
 
-        match root {
-            Ok(output) => Some(
-                output
-                    .stdout
-                    .iter()
-                    .map(|&c| c as char)
-                    .collect::<String>()
-                    .trim()
-                    .to_string(),
-            ),
-            Err(_) => None,
-        }
+    fn get_git_dir(&self) -> Option<String> {
+        rev_parse_path("--absolute-git-dir")
+    }
+
+    fn get_git_common_dir(&self) -> Option<String> {
+        rev_parse_path("--git-common-dir")
     }
 
     fn get_current_branch(&self) -> Option<String> {
@@ -141,31 +232,31 @@ impl GitClient for GitCli {
         }
     }
 
-    fn get_current_commit_title(&self) -> Result<String> {
-        // Executes the `git log -1 --pretty=%s` command to get the title of the current commit.
+    fn get_commit_title(&self, revision: &str) -> Result<String> {
+        // Executes the `git log -1 --pretty=%s <revision>` command to get the title of the commit.
         let output = Command::new("git")
-            .args(["log", "-1", "--pretty=%s"])
+            .args(["log", "-1", "--pretty=%s", revision])
             .output()
-            .context("Failed to get the current commit title")?;
+            .context("Failed to get the commit title")?;
 
         let title = String::from_utf8(output.stdout)
-            .context("Failed to parse the current commit title")?
+            .context("Failed to parse the commit title")?
             .trim()
             .to_string();
 
         Ok(title)
     }
 
-    fn get_current_commit_body(&self) -> Result<String> {
-        // Executes the `git log -1 --pretty=%b` command to get the body of the current commit.
+    fn get_commit_body(&self, revision: &str) -> Result<String> {
+        // Executes the `git log -1 --pretty=%b <revision>` command to get the body of the commit.
         let output = Command::new("git")
-            .args(["log", "-1", "--pretty=%b"])
+            .args(["log", "-1", "--pretty=%b", revision])
             .output()
-            .context("Failed to get the current commit body")?;
+            .context("Failed to get the commit body")?;
 
         // Filters out the "Signed-off-by:" line from the commit body.
         let body = String::from_utf8(output.stdout)
-            .context("Failed to parse the current commit body")?
+            .context("Failed to parse the commit body")?
             .lines()
             .filter(|line| !line.contains("Signed-off-by:"))
             .collect::<Vec<&str>>()
@@ -177,12 +268,16 @@ impl GitClient for GitCli {
     }
 
     fn get_branch_revision(&self, branch: &str) -> Result<String> {
-        // Executes the `git rev-parse <branch>` command to get the revision of the specified branch.
+        // Executes the `git rev-parse --verify <branch>` command to get the revision of the specified branch.
         let output = Command::new("git")
-            .args(["rev-parse", branch])
+            .args(["rev-parse", "--verify", "--quiet", branch])
             .output()
             .context("Failed to get branch revision")?;
 
+        if !output.status.success() {
+            return Err(eyre::eyre!("Branch {} does not exist", branch));
+        }
+
         let revision = String::from_utf8(output.stdout)
             .context("Failed to parse branch revision")?
             .trim()
@@ -298,10 +393,78 @@ impl GitClient for GitCli {
         Ok(())
     }
 
-    fn push_branch(&self, branch: &String) -> Result<()> {
-        // Executes the `git push origin <branch>` command to push the specified branch to the remote repository.
+    fn delete_ref(&self, refname: &str) -> Result<()> {
+        // Executes the `git update-ref -d <refname>` command to delete the reference with the specified name.
+        let output = Command::new("git")
+            .args(["update-ref", "-d", refname])
+            .output()
+            .context("Failed to delete reference")?;
+
+        if !output.status.success() {
+            return Err(eyre::eyre!("Failed to delete reference"));
+        }
+
+        Ok(())
+    }
+
+    fn list_refs(&self, prefix: &str) -> Result<Vec<(String, String)>> {
+        // Executes the `git for-each-ref --format=<format> <prefix>` command to list the references under the specified prefix.
+        let output = Command::new("git")
+            .args(["for-each-ref", "--format=%(refname) %(objectname)", prefix])
+            .output()
+            .context("Failed to list references")?;
+
+        if !output.status.success() {
+            return Err(eyre::eyre!("Failed to list references"));
+        }
+
+        let refs = String::from_utf8(output.stdout)
+            .context("Failed to parse references")?
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(refname, oid)| (refname.to_string(), oid.to_string()))
+            .collect::<Vec<(String, String)>>();
+
+        Ok(refs)
+    }
+
+    fn fetch_branch(&self, remote: &str, branch: &str) -> Result<()> {
+        // A checked out branch has to be fast-forwarded along with the working tree, the others
+        // are updated by `git fetch <remote> <branch>:<branch>`, which refuses to rewrite them.
+        let output = if self.get_current_branch().as_deref() == Some(branch) {
+            Command::new("git")
+                .args(["pull", "--ff-only", remote, branch])
+                .output()
+        } else {
+            Command::new("git")
+                .args(["fetch", remote, &format!("{branch}:{branch}")])
+                .output()
+        }
+        .context("Failed to fetch branch")?;
+
+        if !output.status.success() {
+            return Err(eyre::eyre!(
+                "Failed to fast-forward {branch} to {remote}/{branch}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+            .suggestion(format!(
+                "Reconcile {branch} with {remote}/{branch}, e.g. with `git pull --rebase {remote} {branch}`."
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn push_branch(&self, remote: &str, branch: &str, force: bool) -> Result<()> {
+        // Executes the `git push [--force-with-lease] <remote> <branch>` command to push the specified branch to the remote repository.
+        let mut args = vec!["push"];
+        if force {
+            args.push("--force-with-lease");
+        }
+        args.extend([remote, branch]);
+
         let output = Command::new("git")
-            .args(["push", "origin", branch])
+            .args(args)
             .output()
             .context("Failed to push branch")?;
 
@@ -312,6 +475,177 @@ impl GitClient for GitCli {
         Ok(())
     }
 
+    fn rebase_onto(&self, new_base: &str, upstream: &str, branch: &str) -> Result<RebaseStatus> {
+        // Executes the `git rebase --onto <new_base> <upstream> <branch>` command to move the branch onto its new base.
+        let output = Command::new("git")
+            .args(["rebase", "--onto", new_base, upstream, branch])
+            .output()
+            .context("Failed to rebase branch")?;
+
+        if output.status.success() {
+            return Ok(RebaseStatus::Success);
+        }
+
+        // A rebase that stopped midway is still in progress, any other failure isn't
+        if self.is_rebase_in_progress()? {
+            return Ok(RebaseStatus::Conflict);
+        }
+
+        Err(eyre::eyre!(
+            "Failed to rebase branch {}: {}",
+            branch,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .suggestion("Commit or stash your changes before rebasing.")
+    }
+
+    fn rebase_continue(&self) -> Result<RebaseStatus> {
+        // Executes the `git rebase --continue` command, keeping the commit messages as they are.
+        let output = Command::new("git")
+            .args(["-c", "core.editor=true", "rebase", "--continue"])
+            .output()
+            .context("Failed to continue rebase")?;
+
+        if output.status.success() {
+            return Ok(RebaseStatus::Success);
+        }
+
+        if self.is_rebase_in_progress()? {
+            return Ok(RebaseStatus::Conflict);
+        }
+
+        Err(eyre::eyre!(
+            "Failed to continue rebase: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+
+    fn rebase_abort(&self) -> Result<()> {
+        // Executes the `git rebase --abort` command to restore the branch that was being rebased.
+        let output = Command::new("git")
+            .args(["rebase", "--abort"])
+            .output()
+            .context("Failed to abort rebase")?;
+
+        if !output.status.success() {
+            return Err(eyre::eyre!("Failed to abort rebase"));
+        }
+
+        Ok(())
+    }
+
+    fn is_rebase_in_progress(&self) -> Result<bool> {
+        // Executes the `git rev-parse --git-path <dir>` command to find the state directories a stopped rebase leaves behind.
+        for state_dir in ["rebase-merge", "rebase-apply"] {
+            let output = Command::new("git")
+                .args(["rev-parse", "--git-path", state_dir])
+                .output()
+                .context("Failed to get the rebase state directory")?;
+
+            let path = String::from_utf8(output.stdout)
+                .context("Failed to parse the rebase state directory")?
+                .trim()
+                .to_string();
+
+            if std::path::Path::new(&path).exists() {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn is_ancestor(&self, ancestor: &str, descendant: &str) -> Result<bool> {
+        // Executes the `git merge-base --is-ancestor <ancestor> <descendant>` command, which exits with 1 when it isn't one.
+        // The output is captured so that errors about unknown revisions don't leak to the terminal
+        let output = Command::new("git")
+            .args(["merge-base", "--is-ancestor", ancestor, descendant])
+            .output()
+            .context("Failed to check revision ancestry")?;
+
+        match output.status.code() {
+            Some(0) => Ok(true),
+            Some(1) => Ok(false),
+            _ => Err(eyre::eyre!(
+                "Failed to check revision ancestry: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+        }
+    }
+
+    fn merge_base(&self, first: &str, second: &str) -> Result<String> {
+        // Executes the `git merge-base <first> <second>` command to find their best common ancestor.
+        let output = Command::new("git")
+            .args(["merge-base", first, second])
+            .output()
+            .context("Failed to find the merge base")?;
+
+        if !output.status.success() {
+            return Err(eyre::eyre!("{first} and {second} have no common history"));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn list_commits(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        // Executes the `git rev-list --reverse <from>..<to>` command to list the commits oldest first.
+        let output = Command::new("git")
+            .args(["rev-list", "--reverse", &format!("{from}..{to}")])
+            .output()
+            .context("Failed to list commits")?;
+
+        if !output.status.success() {
+            return Err(eyre::eyre!(
+                "Failed to list the commits between {from} and {to}"
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn reset(&self, revision: &str) -> Result<()> {
+        // Executes the `git reset -q <revision>` command, which keeps the working tree untouched.
+        let output = Command::new("git")
+            .args(["reset", "-q", revision])
+            .output()
+            .context("Failed to reset the current branch")?;
+
+        if !output.status.success() {
+            return Err(eyre::eyre!(
+                "Failed to reset the current branch to {revision}"
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn interactive_add(&self) -> Result<()> {
+        // Executes the `git add -N :/` command so that `git add -p` also offers the untracked files.
+        let output = Command::new("git")
+            .args(["add", "--intent-to-add", ":/"])
+            .output()
+            .context("Failed to add untracked files")?;
+
+        if !output.status.success() {
+            return Err(eyre::eyre!("Failed to add untracked files"));
+        }
+
+        let status = Command::new("git")
+            .args(["add", "--patch"])
+            .stdout(commit_output())
+            .status()
+            .context("Failed to pick hunks")?;
+
+        if !status.success() {
+            return Err(eyre::eyre!("Failed to pick hunks"));
+        }
+
+        Ok(())
+    }
+
     fn add_all(&self) -> Result<()> {
         // Executes the `git add -A` command to add all changes in the working area to the staging area.
         let output = Command::new("git")
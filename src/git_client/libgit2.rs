@@ -0,0 +1,432 @@
+use super::{git_cli::GitCli, CommitStatus, GitClient, RebaseStatus, RepositoryInfo, WorkingArea};
+use eyre::{Context, Result};
+use git2::{ErrorCode, ObjectType, Oid, Repository, Status, StatusOptions};
+use std::{
+    path::Path,
+    sync::{Mutex, MutexGuard},
+};
+
+/// A `GitClient` reading objects, references and the working area through libgit2 instead of
+/// spawning `git`. Operations that involve the user or the network, like committing, rebasing and
+/// pushing, are still delegated to the git CLI so that hooks, editors and credentials keep working.
+pub struct LibGit2 {
+    // libgit2 repositories can't be shared between threads, so every access goes through the lock
+    repository: Mutex<Repository>,
+    cli: GitCli,
+}
+
+impl LibGit2 {
+    /// Opens the repository the current directory belongs to, honouring `GIT_DIR` and friends.
+    pub fn new() -> Result<Self> {
+        let repository = Repository::open_from_env().context("Failed to open git repository")?;
+
+        Ok(Self {
+            repository: Mutex::new(repository),
+            cli: GitCli::new()?,
+        })
+    }
+
+    /// Opens the repository at the specified path.
+    pub fn open(path: &Path) -> Result<Self> {
+        let repository = Repository::open(path).context("Failed to open git repository")?;
+
+        Ok(Self {
+            repository: Mutex::new(repository),
+            cli: GitCli::new()?,
+        })
+    }
+
+    fn repository(&self) -> MutexGuard<'_, Repository> {
+        // A panic while holding the lock can't leave the repository handle in an invalid state
+        self.repository
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Implementation of the `GitClient` trait for the `LibGit2` struct.
+impl GitClient for LibGit2 {
+    fn interactive_commit(&self) -> Result<CommitStatus> {
+        self.cli.interactive_commit()
+    }
+
+    fn interactive_commit_with_message(&self, message: &str) -> Result<CommitStatus> {
+        self.cli.interactive_commit_with_message(message)
+    }
+
+    fn interactive_amend(&self) -> Result<CommitStatus> {
+        self.cli.interactive_amend()
+    }
+
+    fn commit_with_message(&self, message: &str) -> Result<()> {
+        self.cli.commit_with_message(message)
+    }
+
+    fn amend_with_message(&self, message: Option<&str>) -> Result<()> {
+        self.cli.amend_with_message(message)
+    }
+
+    fn checkout(&self, branch: &str) -> Result<()> {
+        self.cli.checkout(branch)
+    }
+
+    fn create_branch(&self, branch: &str) -> Result<String> {
+        self.cli.create_branch(branch)
+    }
+
+    fn get_repository_info(&self, remote: &str) -> Result<RepositoryInfo> {
+        self.cli.get_repository_info(remote)
+    }
+
+    fn list_remotes(&self) -> Result<Vec<String>> {
+        self.cli.list_remotes()
+    }
+
+    fn get_repository_root(&self) -> Option<String> {
+        // Bare repositories have no working directory
+        let repository = self.repository();
+        let workdir = repository.workdir()?.to_str()?;
+
+        Some(workdir.trim_end_matches('/').to_string())
+    }
+
+    fn get_git_dir(&self) -> Option<String> {
+        let repository = self.repository();
+        let path = repository.path().to_str()?;
+
+        Some(path.trim_end_matches('/').to_string())
+    }
+
+    fn get_git_common_dir(&self) -> Option<String> {
+        // The git directory of a linked worktree points to the common one in its `commondir` file
+        let git_dir = self.get_git_dir()?;
+        let Ok(common_dir) = std::fs::read_to_string(Path::new(&git_dir).join("commondir")) else {
+            return Some(git_dir);
+        };
+        let path = Path::new(&git_dir)
+            .join(common_dir.trim())
+            .canonicalize()
+            .ok()?;
+
+        path.to_str().map(str::to_string)
+    }
+
+    fn get_current_branch(&self) -> Option<String> {
+        // Reads HEAD without resolving it, so that branches without commits yet are reported too
+        let repository = self.repository();
+        let head = repository.find_reference("HEAD").ok()?;
+
+        head.symbolic_target()?
+            .strip_prefix("refs/heads/")
+            .map(str::to_string)
+    }
+
+    fn get_commit_title(&self, revision: &str) -> Result<String> {
+        self.cli.get_commit_title(revision)
+    }
+
+    fn get_commit_body(&self, revision: &str) -> Result<String> {
+        self.cli.get_commit_body(revision)
+    }
+
+    fn get_branch_revision(&self, branch: &str) -> Result<String> {
+        let repository = self.repository();
+        let object = repository
+            .revparse_single(branch)
+            .map_err(|_| eyre::eyre!("Branch {} does not exist", branch))?;
+
+        Ok(object.id().to_string())
+    }
+
+    fn delete_branch(&self, branch: &str) -> Result<()> {
+        self.cli.delete_branch(branch)
+    }
+
+    fn get_working_area(&self) -> Result<WorkingArea> {
+        let repository = self.repository();
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false)
+            .renames_head_to_index(true);
+
+        let statuses = repository
+            .statuses(Some(&mut options))
+            .context("Failed to get working area status")?;
+
+        let staged = Status::INDEX_NEW
+            | Status::INDEX_MODIFIED
+            | Status::INDEX_DELETED
+            | Status::INDEX_RENAMED
+            | Status::INDEX_TYPECHANGE;
+        let unstaged =
+            Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE;
+
+        let mut working_area = WorkingArea {
+            staged_files: Vec::new(),
+            unstaged_files: Vec::new(),
+            untracked_files: Vec::new(),
+        };
+        for entry in statuses.iter() {
+            let path = String::from_utf8_lossy(entry.path_bytes()).into_owned();
+            let status = entry.status();
+
+            if status.intersects(staged) {
+                working_area.staged_files.push(path.clone());
+            }
+            if status.intersects(unstaged) {
+                working_area.unstaged_files.push(path.clone());
+            }
+            if status.contains(Status::WT_NEW) {
+                working_area.untracked_files.push(path);
+            }
+        }
+
+        Ok(working_area)
+    }
+
+    fn create_blob(&self, content: &str) -> Result<String> {
+        let oid = self
+            .repository()
+            .blob(content.as_bytes())
+            .context("Failed to create blob")?;
+
+        Ok(oid.to_string())
+    }
+
+    fn read_object(&self, oid: &str) -> Result<String> {
+        let repository = self.repository();
+        let oid = Oid::from_str(oid).context("Invalid object id")?;
+        let blob = repository
+            .find_object(oid, Some(ObjectType::Blob))
+            .and_then(|object| object.peel_to_blob())
+            .context("Failed to read object")?;
+
+        let content =
+            String::from_utf8(blob.content().to_vec()).context("Failed to parse object content")?;
+
+        Ok(content)
+    }
+
+    fn update_ref(&self, refname: &str, oid: &str) -> Result<()> {
+        let oid = Oid::from_str(oid).context("Invalid object id")?;
+
+        self.repository()
+            .reference(refname, oid, true, "gi: update reference")
+            .context("Failed to update reference")?;
+
+        Ok(())
+    }
+
+    fn delete_ref(&self, refname: &str) -> Result<()> {
+        let repository = self.repository();
+        let mut reference = match repository.find_reference(refname) {
+            Ok(reference) => reference,
+            // Like `git update-ref -d`, deleting a reference that doesn't exist succeeds
+            Err(error) if error.code() == ErrorCode::NotFound => return Ok(()),
+            Err(error) => return Err(error).context("Failed to delete reference"),
+        };
+
+        reference.delete().context("Failed to delete reference")?;
+
+        Ok(())
+    }
+
+    fn list_refs(&self, prefix: &str) -> Result<Vec<(String, String)>> {
+        let repository = self.repository();
+        let references = repository
+            .references_glob(&format!("{}*", prefix))
+            .context("Failed to list references")?;
+
+        let mut refs = Vec::new();
+        for reference in references {
+            let reference = reference.context("Failed to list references")?;
+            if let (Some(name), Some(oid)) = (reference.name(), reference.target()) {
+                refs.push((name.to_string(), oid.to_string()));
+            }
+        }
+
+        // Matches the ordering of `git for-each-ref`
+        refs.sort();
+
+        Ok(refs)
+    }
+
+    fn fetch_branch(&self, remote: &str, branch: &str) -> Result<()> {
+        self.cli.fetch_branch(remote, branch)
+    }
+
+    fn push_branch(&self, remote: &str, branch: &str, force: bool) -> Result<()> {
+        self.cli.push_branch(remote, branch, force)
+    }
+
+    fn rebase_onto(&self, new_base: &str, upstream: &str, branch: &str) -> Result<RebaseStatus> {
+        self.cli.rebase_onto(new_base, upstream, branch)
+    }
+
+    fn rebase_continue(&self) -> Result<RebaseStatus> {
+        self.cli.rebase_continue()
+    }
+
+    fn rebase_abort(&self) -> Result<()> {
+        self.cli.rebase_abort()
+    }
+
+    fn is_rebase_in_progress(&self) -> Result<bool> {
+        self.cli.is_rebase_in_progress()
+    }
+
+    fn is_ancestor(&self, ancestor: &str, descendant: &str) -> Result<bool> {
+        self.cli.is_ancestor(ancestor, descendant)
+    }
+
+    fn merge_base(&self, first: &str, second: &str) -> Result<String> {
+        self.cli.merge_base(first, second)
+    }
+
+    fn list_commits(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        self.cli.list_commits(from, to)
+    }
+
+    fn reset(&self, revision: &str) -> Result<()> {
+        self.cli.reset(revision)
+    }
+
+    fn interactive_add(&self) -> Result<()> {
+        self.cli.interactive_add()
+    }
+
+    fn add_all(&self) -> Result<()> {
+        self.cli.add_all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{RepositoryInitOptions, Signature};
+    use tempfile::TempDir;
+
+    /// Creates a repository whose unborn branch is `main`, and opens it with libgit2.
+    fn init() -> (TempDir, LibGit2) {
+        let dir = tempfile::tempdir().unwrap();
+        Repository::init_opts(
+            dir.path(),
+            RepositoryInitOptions::new().initial_head("main"),
+        )
+        .unwrap();
+        let client = LibGit2::open(dir.path()).unwrap();
+
+        (dir, client)
+    }
+
+    /// Writes the files in the working tree and commits them on top of the current branch.
+    fn commit(dir: &TempDir, client: &LibGit2, files: &[(&str, &str)]) -> String {
+        let repository = client.repository();
+        let mut index = repository.index().unwrap();
+        for (name, content) in files {
+            std::fs::write(dir.path().join(name), content).unwrap();
+            index.add_path(Path::new(name)).unwrap();
+        }
+        index.write().unwrap();
+
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let parent = repository
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok());
+        let oid = repository
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "Commit",
+                &tree,
+                parent.as_ref().into_iter().collect::<Vec<_>>().as_slice(),
+            )
+            .unwrap();
+
+        oid.to_string()
+    }
+
+    #[test]
+    fn the_current_branch_is_reported_before_its_first_commit() {
+        let (_dir, client) = init();
+
+        assert_eq!(client.get_current_branch().as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn the_working_area_tells_staged_unstaged_and_untracked_files_apart() {
+        let (dir, client) = init();
+        commit(
+            &dir,
+            &client,
+            &[("lexer.rs", "lexer"), ("parser.rs", "parser")],
+        );
+
+        std::fs::write(dir.path().join("lexer.rs"), "faster lexer").unwrap();
+        std::fs::write(dir.path().join("checker.rs"), "checker").unwrap();
+        {
+            let repository = client.repository();
+            let mut index = repository.index().unwrap();
+            index.add_path(Path::new("checker.rs")).unwrap();
+            index.write().unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), "notes").unwrap();
+
+        let working_area = client.get_working_area().unwrap();
+
+        assert_eq!(working_area.staged_files, vec!["checker.rs"]);
+        assert_eq!(working_area.unstaged_files, vec!["lexer.rs"]);
+        assert_eq!(working_area.untracked_files, vec!["notes.txt"]);
+    }
+
+    #[test]
+    fn references_are_listed_by_name_under_the_prefix() {
+        let (dir, client) = init();
+        let revision = commit(&dir, &client, &[("lexer.rs", "lexer")]);
+        for name in ["refs/gi/parser", "refs/gi/lexer", "refs/other/lexer"] {
+            client.update_ref(name, &revision).unwrap();
+        }
+
+        let refs = client.list_refs("refs/gi/").unwrap();
+
+        assert_eq!(
+            refs,
+            vec![
+                ("refs/gi/lexer".to_string(), revision.clone()),
+                ("refs/gi/parser".to_string(), revision),
+            ]
+        );
+    }
+
+    #[test]
+    fn blobs_are_read_back_as_written() {
+        let (_dir, client) = init();
+
+        let oid = client
+            .create_blob("{\"parentBranchName\":\"main\"}")
+            .unwrap();
+
+        assert_eq!(
+            client.read_object(&oid).unwrap(),
+            "{\"parentBranchName\":\"main\"}"
+        );
+        assert!(client.read_object("not an object id").is_err());
+    }
+
+    #[test]
+    fn deleting_a_missing_reference_succeeds() {
+        let (dir, client) = init();
+        let revision = commit(&dir, &client, &[("lexer.rs", "lexer")]);
+        client.update_ref("refs/gi/lexer", &revision).unwrap();
+
+        client.delete_ref("refs/gi/lexer").unwrap();
+        client.delete_ref("refs/gi/lexer").unwrap();
+
+        assert!(client.list_refs("refs/gi/").unwrap().is_empty());
+    }
+}
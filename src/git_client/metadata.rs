@@ -1,13 +1,21 @@
+use std::collections::BTreeMap;
+
+use eyre::Context;
 use serde::{Deserialize, Serialize};
 
 use super::GitClient;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The prefix under which the metadata of every tracked branch is stored.
+pub const BRANCH_METADATA_REF_PREFIX: &str = "refs/branch-metadata/";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BranchMetadata {
     #[serde(rename = "parentBranchName")]
     parent_branch_name: String,
     #[serde(rename = "parentBranchRevision")]
     parent_branch_revision: String,
+    #[serde(rename = "prNumber", default, skip_serializing_if = "Option::is_none")]
+    pr_number: Option<u64>,
 }
 
 impl BranchMetadata {
@@ -15,20 +23,103 @@ impl BranchMetadata {
         Self {
             parent_branch_name,
             parent_branch_revision,
+            pr_number: None,
         }
     }
+
+    pub fn parent_branch_name(&self) -> &str {
+        &self.parent_branch_name
+    }
+
+    pub fn set_parent_branch_name(&mut self, parent_branch_name: String) {
+        self.parent_branch_name = parent_branch_name;
+    }
+
+    pub fn parent_branch_revision(&self) -> &str {
+        &self.parent_branch_revision
+    }
+
+    pub fn set_parent_branch_revision(&mut self, parent_branch_revision: String) {
+        self.parent_branch_revision = parent_branch_revision;
+    }
+
+    /// The number of the pull request opened from the branch, if it was submitted.
+    pub fn pr_number(&self) -> Option<u64> {
+        self.pr_number
+    }
+
+    pub fn set_pr_number(&mut self, pr_number: Option<u64>) {
+        self.pr_number = pr_number;
+    }
 }
 
 pub fn create_branch_metadata(
-    git_client: &Box<dyn GitClient>,
+    git_client: &dyn GitClient,
     branch_name: String,
     metadata: &BranchMetadata,
 ) -> eyre::Result<()> {
     let metadata = serde_json::to_string(metadata)?;
 
     let object_sha = git_client.create_blob(&metadata)?;
-    let ref_path = format!("refs/branch-metadata/{branch_name}");
+    let ref_path = format!("{BRANCH_METADATA_REF_PREFIX}{branch_name}");
     git_client.update_ref(&ref_path, &object_sha)?;
 
     Ok(())
 }
+
+/// Reads the metadata of a branch.
+///
+/// # Returns
+///
+/// A `Result` containing the metadata of the branch, or `None` if the branch isn't tracked.
+pub fn read_branch_metadata(
+    git_client: &dyn GitClient,
+    branch_name: &str,
+) -> eyre::Result<Option<BranchMetadata>> {
+    let Some(oid) = get_branch_metadata_oid(git_client, branch_name) else {
+        return Ok(None);
+    };
+
+    let content = git_client.read_object(&oid)?;
+    let metadata = serde_json::from_str::<BranchMetadata>(&content)
+        .with_context(|| format!("Failed to parse the metadata of branch {branch_name}"))?;
+
+    Ok(Some(metadata))
+}
+
+/// Returns the ID of the blob holding the metadata of a branch, or `None` if the branch isn't tracked.
+pub fn get_branch_metadata_oid(git_client: &dyn GitClient, branch_name: &str) -> Option<String> {
+    git_client
+        .get_branch_revision(&format!("{BRANCH_METADATA_REF_PREFIX}{branch_name}"))
+        .ok()
+}
+
+/// Deletes the metadata of a branch, leaving the branch itself untouched.
+pub fn delete_branch_metadata(git_client: &dyn GitClient, branch_name: &str) -> eyre::Result<()> {
+    git_client.delete_ref(&format!("{BRANCH_METADATA_REF_PREFIX}{branch_name}"))
+}
+
+/// Reads the metadata of every tracked branch.
+///
+/// # Returns
+///
+/// A map from branch name to its metadata, ordered by branch name.
+pub fn get_all_branch_metadata(
+    git_client: &dyn GitClient,
+) -> eyre::Result<BTreeMap<String, BranchMetadata>> {
+    let mut all_metadata = BTreeMap::new();
+
+    for (refname, oid) in git_client.list_refs(BRANCH_METADATA_REF_PREFIX)? {
+        let Some(branch_name) = refname.strip_prefix(BRANCH_METADATA_REF_PREFIX) else {
+            continue;
+        };
+
+        let content = git_client.read_object(&oid)?;
+        let metadata = serde_json::from_str::<BranchMetadata>(&content)
+            .with_context(|| format!("Failed to parse the metadata of branch {branch_name}"))?;
+
+        all_metadata.insert(branch_name.to_string(), metadata);
+    }
+
+    Ok(all_metadata)
+}
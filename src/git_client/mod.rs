@@ -1,16 +1,41 @@
 pub mod git_cli;
+pub mod libgit2;
 pub mod metadata;
+pub mod remote_url;
 
-use crate::git_provider::SupportedProviders;
+use crate::{git_provider::SupportedProviders, user::settings::get_user_settings};
 
-use self::git_cli::GitCli;
+use self::{git_cli::GitCli, libgit2::LibGit2};
 use eyre::Result;
+use serde::{Deserialize, Serialize};
 
 pub enum CommitStatus {
     Success,
     Aborted,
 }
 
+/// The remote repository the current one is hosted on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepositoryInfo {
+    pub provider: SupportedProviders,
+    /// The host of the provider, e.g. `github.com`.
+    pub host: String,
+    /// The owner of the repository, including every nested group for providers that support them.
+    /// For Azure DevOps, this is the organization.
+    pub owner: String,
+    /// The project the repository belongs to, for providers that group repositories in projects.
+    pub project: Option<String>,
+    pub repo: String,
+    /// The URL of the web server hosting the repository, including its scheme and port, e.g.
+    /// `https://git.corp.example:8443`. Self-hosted providers serve their API under it.
+    pub web_url: String,
+}
+
+pub enum RebaseStatus {
+    Success,
+    Conflict,
+}
+
 #[derive(Debug)]
 pub struct WorkingArea {
     pub staged_files: Vec<String>,
@@ -20,13 +45,13 @@ pub struct WorkingArea {
 
 impl WorkingArea {
     pub fn is_empty(&self) -> bool {
-        return self.staged_files.is_empty()
+        self.staged_files.is_empty()
             && self.unstaged_files.is_empty()
-            && self.untracked_files.is_empty();
+            && self.untracked_files.is_empty()
     }
 
     pub fn has_staged_changes(&self) -> bool {
-        return !self.staged_files.is_empty();
+        !self.staged_files.is_empty()
     }
 }
 
@@ -39,6 +64,46 @@ pub trait GitClient: Send + Sync {
     /// A `Result` containing the commit status on success, or an error on failure.
     fn interactive_commit(&self) -> Result<CommitStatus>;
 
+    /// Performs an interactive commit, with the editor prefilled with the specified message.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message the user starts editing from.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the commit status on success, or an error on failure.
+    fn interactive_commit_with_message(&self, message: &str) -> Result<CommitStatus>;
+
+    /// Performs an interactive amend of the current commit.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the commit status on success, or an error on failure.
+    fn interactive_amend(&self) -> Result<CommitStatus>;
+
+    /// Commits the staged changes with the specified message, without opening an editor.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The commit message, whose first line is the title of the commit.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or failure.
+    fn commit_with_message(&self, message: &str) -> Result<()>;
+
+    /// Amends the current commit with the staged changes, without opening an editor.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The new commit message, or `None` to keep the current one.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or failure.
+    fn amend_with_message(&self, message: Option<&str>) -> Result<()>;
+
     /// Checks out the specified branch.
     ///
     /// # Arguments
@@ -61,11 +126,15 @@ pub trait GitClient: Send + Sync {
     /// A `Result` containing the name of the created branch on success, or an error on failure.
     fn create_branch(&self, branch: &str) -> Result<String>;
 
-    /// Retrieves information about the current Git repository.
+    /// Retrieves information about the repository the specified remote points to.
+    ///
+    /// # Arguments
+    ///
+    /// * `remote` - The name of the remote, e.g. `origin`.
     ///
     /// # Returns
     ///
-    /// A `Result` containing a tuple with three elements: the provider, owner, and repository name.
+    /// A `Result` containing the provider, host, owner, project, and repository name.
     ///
     /// # Errors
     ///
@@ -73,7 +142,15 @@ pub trait GitClient: Send + Sync {
     ///
     /// * The `git` command fails to execute.
     /// * The current directory is not a Git repository.
-    fn get_repository_info(&self) -> Result<(SupportedProviders, String, String)>;
+    /// * The remote doesn't exist or its URL can't be parsed.
+    fn get_repository_info(&self, remote: &str) -> Result<RepositoryInfo>;
+
+    /// Lists the names of the remotes of the current repository.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the names of the remotes on success, or an error on failure.
+    fn list_remotes(&self) -> Result<Vec<String>>;
 
     /// Retrieves the root directory of the current Git repository.
     ///
@@ -82,6 +159,22 @@ pub trait GitClient: Send + Sync {
     /// The root directory of the current Git repository as an `Option<String>`.
     fn get_repository_root(&self) -> Option<String>;
 
+    /// Retrieves the git directory of the current worktree, e.g. `.git/worktrees/<name>` in a
+    /// linked worktree, where the state of an operation in progress in the worktree is kept.
+    ///
+    /// # Returns
+    ///
+    /// The absolute path of the git directory as an `Option<String>`.
+    fn get_git_dir(&self) -> Option<String>;
+
+    /// Retrieves the git directory shared by every worktree of the current repository, where the
+    /// settings of the repository are kept.
+    ///
+    /// # Returns
+    ///
+    /// The absolute path of the common git directory as an `Option<String>`.
+    fn get_git_common_dir(&self) -> Option<String>;
+
     /// Retrieves the name of the current branch.
     ///
     /// # Returns
@@ -94,14 +187,40 @@ pub trait GitClient: Send + Sync {
     /// # Returns
     ///
     /// The title of the current commit as a `Result<String>`.
-    fn get_current_commit_title(&self) -> Result<String>;
+    fn get_current_commit_title(&self) -> Result<String> {
+        self.get_commit_title("HEAD")
+    }
 
     /// Retrieves the body of the current commit.
     ///
     /// # Returns
     ///
     /// The body of the current commit as a `Result<String>`.
-    fn get_current_commit_body(&self) -> Result<String>;
+    fn get_current_commit_body(&self) -> Result<String> {
+        self.get_commit_body("HEAD")
+    }
+
+    /// Retrieves the title of the specified commit.
+    ///
+    /// # Arguments
+    ///
+    /// * `revision` - The revision of the commit, e.g. a branch name.
+    ///
+    /// # Returns
+    ///
+    /// The title of the commit as a `Result<String>`.
+    fn get_commit_title(&self, revision: &str) -> Result<String>;
+
+    /// Retrieves the body of the specified commit, without its `Signed-off-by:` lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `revision` - The revision of the commit, e.g. a branch name.
+    ///
+    /// # Returns
+    ///
+    /// The body of the commit as a `Result<String>`.
+    fn get_commit_body(&self, revision: &str) -> Result<String>;
 
     /// Retrieves the revision of the specified branch.
     ///
@@ -166,16 +285,141 @@ pub trait GitClient: Send + Sync {
     /// A `Result` indicating success or failure.
     fn update_ref(&self, refname: &str, oid: &str) -> Result<()>;
 
-    /// Pushes the specified branch to the remote repository.
+    /// Deletes the reference with the specified name.
+    ///
+    /// # Arguments
+    ///
+    /// * `refname` - The name of the reference to delete.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or failure.
+    fn delete_ref(&self, refname: &str) -> Result<()>;
+
+    /// Lists the references whose names start with the specified prefix.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - The prefix of the references to list (e.g. `refs/branch-metadata/`).
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a list of `(refname, oid)` pairs on success, or an error on failure.
+    fn list_refs(&self, prefix: &str) -> Result<Vec<(String, String)>>;
+
+    /// Fetches a branch from a remote repository and fast-forwards the local branch to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `remote` - The name of the remote to fetch from.
+    /// * `branch` - The name of the branch, both on the remote and locally.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success, or an error if the local branch can't be fast-forwarded.
+    fn fetch_branch(&self, remote: &str, branch: &str) -> Result<()>;
+
+    /// Pushes the specified branch to a remote repository.
     ///
     /// # Arguments
     ///
+    /// * `remote` - The name of the remote to push to.
     /// * `branch` - The name of the branch to push.
+    /// * `force` - Whether to overwrite the remote branch if it was rewritten locally.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or failure.
+    fn push_branch(&self, remote: &str, branch: &str, force: bool) -> Result<()>;
+
+    /// Rebases the commits of a branch that are not reachable from `upstream` onto `new_base`.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_base` - The revision to replay the commits onto.
+    /// * `upstream` - The revision the branch was originally based on.
+    /// * `branch` - The name of the branch to rebase.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the rebase status on success, or an error if the rebase could not be started.
+    fn rebase_onto(&self, new_base: &str, upstream: &str, branch: &str) -> Result<RebaseStatus>;
+
+    /// Continues a rebase that stopped on a conflict.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the rebase status on success, or an error if the rebase could not be continued.
+    fn rebase_continue(&self) -> Result<RebaseStatus>;
+
+    /// Aborts the rebase in progress, restoring the branch that was being rebased.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or failure.
+    fn rebase_abort(&self) -> Result<()>;
+
+    /// Checks whether a rebase is currently in progress.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `true` if a rebase stopped midway, or an error on failure.
+    fn is_rebase_in_progress(&self) -> Result<bool>;
+
+    /// Checks whether a revision is an ancestor of another one.
+    ///
+    /// # Arguments
+    ///
+    /// * `ancestor` - The revision that might be an ancestor.
+    /// * `descendant` - The revision that might be a descendant.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `true` if `ancestor` is reachable from `descendant`, or an error on failure.
+    fn is_ancestor(&self, ancestor: &str, descendant: &str) -> Result<bool>;
+
+    /// Finds the best common ancestor of two revisions.
+    ///
+    /// # Arguments
+    ///
+    /// * `first` - The first revision, e.g. a branch name.
+    /// * `second` - The second revision.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the ID of the common ancestor, or an error if the revisions share no history.
+    fn merge_base(&self, first: &str, second: &str) -> Result<String>;
+
+    /// Lists the commits reachable from a revision but not from another one.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - The revision whose history is excluded, e.g. the parent of a branch.
+    /// * `to` - The revision whose history is listed, e.g. the branch itself.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the IDs of the commits, oldest first.
+    fn list_commits(&self, from: &str, to: &str) -> Result<Vec<String>>;
+
+    /// Moves the current branch to the specified revision, keeping the working tree as it is so that
+    /// the changes of the commits left behind become unstaged.
+    ///
+    /// # Arguments
+    ///
+    /// * `revision` - The revision to move the current branch to.
     ///
     /// # Returns
     ///
     /// A `Result` indicating success or failure.
-    fn push_branch(&self, branch: &String) -> Result<()>;
+    fn reset(&self, revision: &str) -> Result<()>;
+
+    /// Lets the user pick the hunks of the working area to stage, including the ones of untracked files.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success, or an error if the hunks could not be picked.
+    fn interactive_add(&self) -> Result<()>;
 
     /// Adds all changes in the working area to the staging area.
     ///
@@ -185,6 +429,33 @@ pub trait GitClient: Send + Sync {
     fn add_all(&self) -> Result<()>;
 }
 
+/// The implementations of `GitClient` the user can choose between.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GitBackend {
+    /// Reads the repository through libgit2, spawning `git` only for interactive and network operations.
+    Native,
+    /// Spawns `git` for every operation.
+    #[default]
+    Cli,
+}
+
 pub fn get_git_client() -> Result<Box<dyn GitClient>> {
-    return Ok(Box::new(GitCli::new()?));
+    let backend = get_user_settings()?.git_backend();
+
+    match backend {
+        GitBackend::Native => match LibGit2::new() {
+            Ok(client) => Ok(Box::new(client)),
+            // Outside of a repository, the CLI backend reports the error with the usual suggestions
+            Err(error)
+                if error
+                    .downcast_ref::<git2::Error>()
+                    .is_some_and(|error| error.code() == git2::ErrorCode::NotFound) =>
+            {
+                Ok(Box::new(GitCli::new()?))
+            }
+            Err(error) => Err(error),
+        },
+        GitBackend::Cli => Ok(Box::new(GitCli::new()?)),
+    }
 }
@@ -0,0 +1,192 @@
+use async_trait::async_trait;
+use eyre::{OptionExt, Result};
+use reqwest::{Client, Method, RequestBuilder};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{send_json, GitProvider, PullRequest, PullRequestState};
+use crate::git_client::RepositoryInfo;
+
+/// The version of the Azure DevOps REST API gi talks to.
+const API_VERSION: &str = "7.0";
+
+/// Azure DevOps Repos, reached through the Azure DevOps REST API.
+#[derive(Debug, Clone)]
+pub struct AzureDevOps {
+    /// The base URL of the API, e.g. `https://dev.azure.com`.
+    api_url: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Repository {
+    web_url: String,
+}
+
+/// A pull request as returned by the Azure DevOps API.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct AzurePullRequest {
+    pull_request_id: u64,
+    repository: Repository,
+    target_ref_name: String,
+    title: String,
+    description: Option<String>,
+    /// One of `active`, `completed` or `abandoned`.
+    #[serde(default)]
+    status: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Page {
+    value: Vec<AzurePullRequest>,
+}
+
+impl From<AzurePullRequest> for PullRequest {
+    fn from(pr: AzurePullRequest) -> Self {
+        Self {
+            number: pr.pull_request_id,
+            url: format!(
+                "{}/pullrequest/{}",
+                pr.repository.web_url, pr.pull_request_id
+            ),
+            base: pr
+                .target_ref_name
+                .trim_start_matches("refs/heads/")
+                .to_string(),
+            title: pr.title,
+            body: pr.description.unwrap_or_default(),
+            state: match pr.status.as_str() {
+                "completed" => PullRequestState::Merged,
+                "abandoned" => PullRequestState::Closed,
+                _ => PullRequestState::Open,
+            },
+        }
+    }
+}
+
+impl AzureDevOps {
+    pub fn new() -> Result<Self> {
+        Ok(Self::with_api_url("https://dev.azure.com".to_string()))
+    }
+
+    /// Creates an Azure DevOps provider talking to the API at the specified URL.
+    pub fn with_api_url(api_url: String) -> Self {
+        Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn request(
+        &self,
+        method: Method,
+        repository: &RepositoryInfo,
+        path: &str,
+    ) -> Result<RequestBuilder> {
+        let token = self.get_token()?;
+        let project = repository
+            .project
+            .as_deref()
+            .ok_or_eyre("Azure DevOps repositories must belong to a project")?;
+        let url = format!(
+            "{}/{}/{}/_apis/git/repositories/{}/pullrequests{}",
+            self.api_url, repository.owner, project, repository.repo, path
+        );
+
+        // Personal access tokens are sent as the password of a user with an empty name
+        Ok(Client::new()
+            .request(method, url)
+            .query(&[("api-version", API_VERSION)])
+            .basic_auth("", Some(token)))
+    }
+}
+
+#[async_trait]
+impl GitProvider for AzureDevOps {
+    fn token_key(&self) -> String {
+        "dev.azure.com".to_string()
+    }
+
+    fn token_prompt(&self) -> String {
+        "Set up your Azure DevOps personal access token with the Code (Read & Write) scope. You can generate one at:
+https://dev.azure.com/<organization>/_usersSettings/tokens\n"
+            .to_string()
+    }
+
+    fn pull_request_reference(&self, number: u64) -> String {
+        format!("!{number}")
+    }
+
+    async fn create_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        _fork: Option<&RepositoryInfo>,
+        branch: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let request = self.request(Method::POST, repository, "")?.json(&json!({
+            "sourceRefName": format!("refs/heads/{branch}"),
+            "targetRefName": format!("refs/heads/{base}"),
+            "title": title,
+            "description": body,
+        }));
+
+        let pr: AzurePullRequest = send_json(request, "Failed to create pull request").await?;
+
+        Ok(pr.into())
+    }
+
+    async fn find_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        _fork: Option<&RepositoryInfo>,
+        branch: &str,
+    ) -> Result<Option<PullRequest>> {
+        let request = self.request(Method::GET, repository, "")?.query(&[
+            (
+                "searchCriteria.sourceRefName",
+                format!("refs/heads/{branch}"),
+            ),
+            ("searchCriteria.status", "active".to_string()),
+        ]);
+
+        let page: Page = send_json(request, "Failed to look up pull requests").await?;
+
+        Ok(page.value.into_iter().next().map(PullRequest::from))
+    }
+
+    async fn get_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+    ) -> Result<PullRequest> {
+        let request = self.request(Method::GET, repository, &format!("/{number}"))?;
+
+        let pr: AzurePullRequest = send_json(request, "Failed to get pull request").await?;
+
+        Ok(pr.into())
+    }
+
+    async fn update_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let request = self
+            .request(Method::PATCH, repository, &format!("/{number}"))?
+            .json(&json!({
+                "targetRefName": format!("refs/heads/{base}"),
+                "title": title,
+                "description": body,
+            }));
+
+        let pr: AzurePullRequest = send_json(request, "Failed to update pull request").await?;
+
+        Ok(pr.into())
+    }
+}
@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use eyre::Result;
+use reqwest::{Client, Method, RequestBuilder};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{send_json, with_credentials, GitProvider, PullRequest, PullRequestState};
+use crate::git_client::RepositoryInfo;
+
+/// Bitbucket Cloud, reached through its `api.bitbucket.org/2.0` REST API.
+#[derive(Debug, Clone)]
+pub struct BitbucketCloud {
+    /// The base URL of the API, e.g. `https://api.bitbucket.org/2.0`.
+    api_url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Branch {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Endpoint {
+    branch: Branch,
+}
+
+#[derive(Deserialize, Debug)]
+struct Link {
+    href: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Links {
+    html: Link,
+}
+
+/// A pull request as returned by the Bitbucket Cloud API.
+#[derive(Deserialize, Debug)]
+struct BitbucketPullRequest {
+    id: u64,
+    links: Links,
+    destination: Endpoint,
+    title: String,
+    description: Option<String>,
+    /// One of `OPEN`, `MERGED`, `DECLINED` or `SUPERSEDED`.
+    #[serde(default)]
+    state: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Page {
+    values: Vec<BitbucketPullRequest>,
+}
+
+impl From<BitbucketPullRequest> for PullRequest {
+    fn from(pr: BitbucketPullRequest) -> Self {
+        Self {
+            number: pr.id,
+            url: pr.links.html.href,
+            base: pr.destination.branch.name,
+            title: pr.title,
+            body: pr.description.unwrap_or_default(),
+            state: match pr.state.as_str() {
+                "MERGED" => PullRequestState::Merged,
+                "DECLINED" | "SUPERSEDED" => PullRequestState::Closed,
+                _ => PullRequestState::Open,
+            },
+        }
+    }
+}
+
+impl BitbucketCloud {
+    pub fn new() -> Result<Self> {
+        Ok(Self::with_api_url(
+            "https://api.bitbucket.org/2.0".to_string(),
+        ))
+    }
+
+    /// Creates a Bitbucket Cloud provider talking to the API at the specified URL.
+    pub fn with_api_url(api_url: String) -> Self {
+        Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn request(
+        &self,
+        method: Method,
+        repository: &RepositoryInfo,
+        path: &str,
+    ) -> Result<RequestBuilder> {
+        let token = self.get_token()?;
+        let url = format!(
+            "{}/repositories/{}/{}/pullrequests{}",
+            self.api_url, repository.owner, repository.repo, path
+        );
+
+        Ok(with_credentials(Client::new().request(method, url), &token))
+    }
+}
+
+#[async_trait]
+impl GitProvider for BitbucketCloud {
+    fn token_key(&self) -> String {
+        "bitbucket.org".to_string()
+    }
+
+    fn token_prompt(&self) -> String {
+        "Set up your Bitbucket credentials, either as <username>:<app password> or as an access token with the pullrequest:write scope. You can generate an app password at:
+https://bitbucket.org/account/settings/app-passwords/new\n"
+            .to_string()
+    }
+
+    async fn create_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        _fork: Option<&RepositoryInfo>,
+        branch: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let request = self.request(Method::POST, repository, "")?.json(&json!({
+            "title": title,
+            "description": body,
+            "source": { "branch": { "name": branch } },
+            "destination": { "branch": { "name": base } },
+        }));
+
+        let pr: BitbucketPullRequest = send_json(request, "Failed to create pull request").await?;
+
+        Ok(pr.into())
+    }
+
+    async fn find_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        _fork: Option<&RepositoryInfo>,
+        branch: &str,
+    ) -> Result<Option<PullRequest>> {
+        let query = format!("source.branch.name=\"{branch}\" AND state=\"OPEN\"");
+        let request = self
+            .request(Method::GET, repository, "")?
+            .query(&[("q", query)]);
+
+        let page: Page = send_json(request, "Failed to look up pull requests").await?;
+
+        Ok(page.values.into_iter().next().map(PullRequest::from))
+    }
+
+    async fn get_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+    ) -> Result<PullRequest> {
+        let request = self.request(Method::GET, repository, &format!("/{number}"))?;
+
+        let pr: BitbucketPullRequest = send_json(request, "Failed to get pull request").await?;
+
+        Ok(pr.into())
+    }
+
+    async fn update_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let request = self
+            .request(Method::PUT, repository, &format!("/{number}"))?
+            .json(&json!({
+                "title": title,
+                "description": body,
+                "destination": { "branch": { "name": base } },
+            }));
+
+        let pr: BitbucketPullRequest = send_json(request, "Failed to update pull request").await?;
+
+        Ok(pr.into())
+    }
+}
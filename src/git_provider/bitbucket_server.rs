@@ -0,0 +1,223 @@
+use async_trait::async_trait;
+use eyre::{OptionExt, Result};
+use reqwest::{Client, Method, RequestBuilder};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{send_json, with_credentials, GitProvider, PullRequest, PullRequestState};
+use crate::git_client::RepositoryInfo;
+
+/// A Bitbucket Server or Data Center instance, reached through its `/rest/api/1.0` REST API.
+#[derive(Debug, Clone)]
+pub struct BitbucketServer {
+    /// The base URL of the API, e.g. `https://bitbucket.example.com/rest/api/1.0`.
+    api_url: String,
+    /// The host of the instance, which tokens are stored under.
+    host: String,
+    /// The URL of the instance, used to point the user at its token settings.
+    web_url: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Ref {
+    display_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Link {
+    href: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Links {
+    #[serde(rename = "self")]
+    self_links: Vec<Link>,
+}
+
+/// A pull request as returned by the Bitbucket Server API.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ServerPullRequest {
+    id: u64,
+    /// The version the pull request is at, which updates have to reference.
+    version: u64,
+    links: Links,
+    to_ref: Ref,
+    title: String,
+    description: Option<String>,
+    /// One of `OPEN`, `MERGED` or `DECLINED`.
+    #[serde(default)]
+    state: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Page {
+    values: Vec<ServerPullRequest>,
+}
+
+impl TryFrom<ServerPullRequest> for PullRequest {
+    type Error = eyre::Report;
+
+    fn try_from(pr: ServerPullRequest) -> Result<Self> {
+        Ok(Self {
+            number: pr.id,
+            url: pr
+                .links
+                .self_links
+                .into_iter()
+                .next()
+                .ok_or_eyre("Failed to get pull request URL")?
+                .href,
+            base: pr.to_ref.display_id,
+            title: pr.title,
+            body: pr.description.unwrap_or_default(),
+            state: match pr.state.as_str() {
+                "MERGED" => PullRequestState::Merged,
+                "DECLINED" => PullRequestState::Closed,
+                _ => PullRequestState::Open,
+            },
+        })
+    }
+}
+
+impl BitbucketServer {
+    /// Creates a Bitbucket Server provider for the instance hosting the specified repository.
+    pub fn new(repository: &RepositoryInfo) -> Result<Self> {
+        Ok(Self::with_api_url(
+            repository,
+            format!("{}/rest/api/1.0", repository.web_url),
+        ))
+    }
+
+    /// Creates a Bitbucket Server provider talking to the API at the specified URL.
+    pub fn with_api_url(repository: &RepositoryInfo, api_url: String) -> Self {
+        Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            host: repository.host.clone(),
+            web_url: repository.web_url.clone(),
+        }
+    }
+
+    fn request(
+        &self,
+        method: Method,
+        repository: &RepositoryInfo,
+        path: &str,
+    ) -> Result<RequestBuilder> {
+        let token = self.get_token()?;
+
+        // HTTPS clone URLs prefix the project key with `scm/`
+        let owner = repository.owner.as_str();
+        let project = owner.strip_prefix("scm/").unwrap_or(owner);
+        let url = format!(
+            "{}/projects/{}/repos/{}/pull-requests{}",
+            self.api_url, project, repository.repo, path
+        );
+
+        Ok(with_credentials(Client::new().request(method, url), &token))
+    }
+
+    async fn get_server_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+    ) -> Result<ServerPullRequest> {
+        let request = self.request(Method::GET, repository, &format!("/{number}"))?;
+
+        send_json(request, "Failed to get pull request").await
+    }
+}
+
+#[async_trait]
+impl GitProvider for BitbucketServer {
+    fn token_key(&self) -> String {
+        self.host.clone()
+    }
+
+    fn token_prompt(&self) -> String {
+        format!(
+            "Set up your Bitbucket HTTP access token with repository write permissions, or <username>:<password>. You can generate a token at:
+{}/plugins/servlet/access-tokens/manage\n",
+            self.web_url
+        )
+    }
+
+    async fn create_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        _fork: Option<&RepositoryInfo>,
+        branch: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let request = self.request(Method::POST, repository, "")?.json(&json!({
+            "title": title,
+            "description": body,
+            "fromRef": { "id": format!("refs/heads/{branch}") },
+            "toRef": { "id": format!("refs/heads/{base}") },
+        }));
+
+        let pr: ServerPullRequest = send_json(request, "Failed to create pull request").await?;
+
+        pr.try_into()
+    }
+
+    async fn find_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        _fork: Option<&RepositoryInfo>,
+        branch: &str,
+    ) -> Result<Option<PullRequest>> {
+        let request = self.request(Method::GET, repository, "")?.query(&[
+            ("at", format!("refs/heads/{branch}")),
+            ("direction", "OUTGOING".to_string()),
+            ("state", "OPEN".to_string()),
+        ]);
+
+        let page: Page = send_json(request, "Failed to look up pull requests").await?;
+
+        page.values
+            .into_iter()
+            .next()
+            .map(PullRequest::try_from)
+            .transpose()
+    }
+
+    async fn get_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+    ) -> Result<PullRequest> {
+        self.get_server_pull_request(repository, number)
+            .await?
+            .try_into()
+    }
+
+    async fn update_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        // Updates are rejected unless they reference the latest version of the pull request
+        let current = self.get_server_pull_request(repository, number).await?;
+
+        let request = self
+            .request(Method::PUT, repository, &format!("/{number}"))?
+            .json(&json!({
+                "version": current.version,
+                "title": title,
+                "description": body,
+                "toRef": { "id": format!("refs/heads/{base}") },
+            }));
+
+        let pr: ServerPullRequest = send_json(request, "Failed to update pull request").await?;
+
+        pr.try_into()
+    }
+}
@@ -0,0 +1,232 @@
+use async_trait::async_trait;
+use color_eyre::Section;
+use eyre::Result;
+use reqwest::{Client, Method, RequestBuilder};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{send_json, GitProvider, PullRequest, PullRequestState};
+use crate::git_client::RepositoryInfo;
+
+/// How many pull requests to request per page when looking one up by branch, which is the
+/// default maximum page size of Gitea instances.
+const PAGE_SIZE: u32 = 50;
+
+/// How many pages of open pull requests to go through at most when looking one up by branch.
+const MAX_PAGES: u32 = 100;
+
+/// A Gitea or Forgejo instance, reached through its `/api/v1` REST API.
+#[derive(Debug, Clone)]
+pub struct Gitea {
+    /// The base URL of the API, e.g. `https://codeberg.org/api/v1`.
+    api_url: String,
+    /// The host of the instance, which tokens are stored under.
+    host: String,
+    /// The URL of the instance, used to point the user at its token settings.
+    web_url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct BranchRef {
+    #[serde(rename = "ref")]
+    ref_field: String,
+    /// The repository the branch lives in, which is missing once a fork is deleted.
+    #[serde(default)]
+    repo: Option<BranchRepository>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BranchRepository {
+    owner: RepositoryOwner,
+}
+
+#[derive(Deserialize, Debug)]
+struct RepositoryOwner {
+    login: String,
+}
+
+impl BranchRef {
+    /// Whether this is the specified branch of a repository owned by the specified user.
+    fn is(&self, owner: &str, branch: &str) -> bool {
+        self.ref_field == branch
+            && self
+                .repo
+                .as_ref()
+                .is_some_and(|repo| repo.owner.login.eq_ignore_ascii_case(owner))
+    }
+}
+
+/// A pull request as returned by the Gitea API.
+#[derive(Deserialize, Debug)]
+struct GiteaPullRequest {
+    number: u64,
+    html_url: String,
+    head: BranchRef,
+    base: BranchRef,
+    title: String,
+    body: Option<String>,
+    /// Either `open` or `closed`, merged pull requests being closed ones.
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    merged: bool,
+}
+
+impl From<GiteaPullRequest> for PullRequest {
+    fn from(pr: GiteaPullRequest) -> Self {
+        Self {
+            number: pr.number,
+            url: pr.html_url,
+            base: pr.base.ref_field,
+            title: pr.title,
+            body: pr.body.unwrap_or_default(),
+            state: match (pr.merged, pr.state.as_str()) {
+                (true, _) => PullRequestState::Merged,
+                (false, "closed") => PullRequestState::Closed,
+                _ => PullRequestState::Open,
+            },
+        }
+    }
+}
+
+impl Gitea {
+    /// Creates a Gitea provider for the instance hosting the specified repository.
+    pub fn new(repository: &RepositoryInfo) -> Result<Self> {
+        Ok(Self::with_api_url(
+            repository,
+            format!("{}/api/v1", repository.web_url),
+        ))
+    }
+
+    /// Creates a Gitea provider talking to the API at the specified URL.
+    pub fn with_api_url(repository: &RepositoryInfo, api_url: String) -> Self {
+        Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            host: repository.host.clone(),
+            web_url: repository.web_url.clone(),
+        }
+    }
+
+    fn request(
+        &self,
+        method: Method,
+        repository: &RepositoryInfo,
+        path: &str,
+    ) -> Result<RequestBuilder> {
+        let token = self.get_token()?;
+        let url = format!(
+            "{}/repos/{}/{}/pulls{}",
+            self.api_url, repository.owner, repository.repo, path
+        );
+
+        Ok(Client::new()
+            .request(method, url)
+            .header("Authorization", format!("token {token}")))
+    }
+}
+
+#[async_trait]
+impl GitProvider for Gitea {
+    fn token_key(&self) -> String {
+        self.host.clone()
+    }
+
+    fn token_prompt(&self) -> String {
+        format!(
+            "Set up your Gitea access token with the write:repository scope. You can generate one at:
+{}/user/settings/applications\n",
+            self.web_url
+        )
+    }
+
+    async fn create_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        _fork: Option<&RepositoryInfo>,
+        branch: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let request = self.request(Method::POST, repository, "")?.json(&json!({
+            "head": branch,
+            "base": base,
+            "title": title,
+            "body": body,
+        }));
+
+        let pr: GiteaPullRequest = send_json(request, "Failed to create pull request").await?;
+
+        Ok(pr.into())
+    }
+
+    async fn find_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        fork: Option<&RepositoryInfo>,
+        branch: &str,
+    ) -> Result<Option<PullRequest>> {
+        // Forks may have a branch of the same name, so the owner of the head has to match too
+        let owner = &fork.unwrap_or(repository).owner;
+
+        // The API can't filter by head branch, so go through the open pull requests page by page
+        for page in 1..=MAX_PAGES {
+            let request = self.request(Method::GET, repository, "")?.query(&[
+                ("state", "open".to_string()),
+                ("page", page.to_string()),
+                ("limit", PAGE_SIZE.to_string()),
+            ]);
+
+            let prs: Vec<GiteaPullRequest> =
+                send_json(request, "Failed to look up pull requests").await?;
+            let last_page = prs.len() < PAGE_SIZE as usize;
+
+            if let Some(pr) = prs.into_iter().find(|pr| pr.head.is(owner, branch)) {
+                return Ok(Some(pr.into()));
+            }
+            // A short page is the last one, even from servers ignoring the page parameter
+            if last_page {
+                return Ok(None);
+            }
+        }
+
+        Err(eyre::eyre!(
+            "Failed to look up pull requests: more than {} pages of open pull requests",
+            MAX_PAGES
+        ))
+        .suggestion("Check the API URL configured with `gi host`, whose server may ignore paging.")
+    }
+
+    async fn get_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+    ) -> Result<PullRequest> {
+        let request = self.request(Method::GET, repository, &format!("/{number}"))?;
+
+        let pr: GiteaPullRequest = send_json(request, "Failed to get pull request").await?;
+
+        Ok(pr.into())
+    }
+
+    async fn update_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let request = self
+            .request(Method::PATCH, repository, &format!("/{number}"))?
+            .json(&json!({
+                "base": base,
+                "title": title,
+                "body": body,
+            }));
+
+        let pr: GiteaPullRequest = send_json(request, "Failed to update pull request").await?;
+
+        Ok(pr.into())
+    }
+}
@@ -1,143 +1,198 @@
-use std::{
-    fs::{self, create_dir_all, File},
-    path::PathBuf,
-};
-
+use async_trait::async_trait;
 use color_eyre::Section;
-use dialoguer::theme::ColorfulTheme;
-use dirs;
 use eyre::{Context, OptionExt, Result};
+use octocrab::models::IssueState;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::os::unix::fs::PermissionsExt;
 
-use super::GitProvider;
 use super::SupportedProviders;
+use super::{GitProvider, PullRequest, PullRequestState};
+use crate::git_client::RepositoryInfo;
 
-use crate::{
-    git_client,
-    git_provider::{ask_for_pr_body, ask_for_pr_title},
-};
+/// The host of the public GitHub instance.
+const GITHUB_HOST: &str = "github.com";
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
-pub struct GitHub {}
+pub struct GitHub {
+    /// The host of the instance, e.g. `github.com` or a GitHub Enterprise Server host.
+    host: String,
+    /// The URL of the instance, e.g. `https://github.com`.
+    web_url: String,
+    /// The base URL of the API, or `None` to use the default one of the host.
+    api_url: Option<String>,
+}
 
 impl GitHub {
-    pub fn new() -> Result<Self> {
-        Ok(Self {})
+    /// Creates a GitHub provider for the instance hosting the specified repository.
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The repository, whose host tells github.com apart from GitHub Enterprise Server.
+    /// * `api_url` - The base URL of the API, or `None` to use the default one of the host.
+    pub fn new(repository: &RepositoryInfo, api_url: Option<String>) -> Result<Self> {
+        Ok(Self {
+            host: repository.host.clone(),
+            web_url: repository.web_url.clone(),
+            api_url,
+        })
     }
 }
 
+#[async_trait]
 impl GitProvider for GitHub {
-    fn ask_for_token(&self, path: &PathBuf) -> Result<String> {
-        let token = dialoguer::Input::<String>::with_theme(&ColorfulTheme::default())
-            .with_prompt(
-                "Set up your GitHub personal access token. You can generate one at:
-https://github.com/settings/tokens/new?description=gi&scopes=repo,read:org,read:user,user:email\n",
-            )
-            .interact()?;
-
-        let data = json!({
-            SupportedProviders::GitHub.to_string(): token
-        });
-
-        fs::write(path, data.to_string())
-            .context("Failed to write to token file")
-            .suggestion("Check if you have write permissions")?;
-
-        Ok(token)
+    fn token_key(&self) -> String {
+        self.host.clone()
     }
 
-    fn get_token(&self) -> Result<String> {
-        let home_dir = dirs::home_dir().ok_or_eyre("Failed to get home directory")?;
-        let home_str = home_dir
-            .to_str()
-            .ok_or_eyre("Home directory isn't valid unicode")?;
-        let token_file = [home_str, ".config", "gi", "token"]
-            .iter()
-            .collect::<PathBuf>();
-
-        if !token_file.exists() {
-            create_dir_all([home_str, ".config", "gi"].iter().collect::<PathBuf>())
-                .context("Failed to create gi config directory")
-                .suggestion("Check if you have write permissions to the .config directory")?;
-
-            File::create_new(&token_file)
-                .context("Failed to create token file")
-                .suggestion("Check if you have write permissions to the .config/gi directory")?;
-
-            /* TODO: Implement setting file permissions on Windows
-            At the time of writing this code, fs::Permissions::from_mode only
-            works on Unix systems. In the future, we'll find a way to perform
-            the same operation on Windows. */
-
-            let permissions = fs::Permissions::from_mode(0o600);
-            fs::set_permissions(&token_file, permissions)
-                .context("Failed to set permissions on token file")?;
-
-            return self.ask_for_token(&token_file);
-        }
+    fn legacy_token_key(&self) -> Option<String> {
+        // Tokens used to be stored by provider name, back when only github.com was supported
+        (self.host == GITHUB_HOST).then(|| SupportedProviders::GitHub.to_string())
+    }
 
-        let data = fs::read_to_string(&token_file)
-            .context("Failed to read file")
-            .suggestion("Check if you have read permissions to the token file")?;
-        let deserealized = serde_json::from_str::<serde_json::Value>(&data);
-
-        match deserealized {
-            Ok(value) => match value[SupportedProviders::GitHub.to_string()].as_str() {
-                Some(token) => Ok(token.to_string()),
-                None => self.ask_for_token(&token_file),
-            },
-            Err(_) => self.ask_for_token(&token_file),
-        }
+    fn supports_forks(&self) -> bool {
+        true
+    }
+
+    fn token_prompt(&self) -> String {
+        format!(
+            "Set up your GitHub personal access token. You can generate one at:
+{}/settings/tokens/new?description=gi&scopes=repo,read:org,read:user,user:email\n",
+            self.web_url
+        )
     }
 
     async fn create_pull_request(
         &self,
-        owner: &String,
-        repo: &String,
-        branch: &String,
-        trunk: &String,
-    ) -> Result<()> {
-        let token = self.get_token()?;
+        repository: &RepositoryInfo,
+        fork: Option<&RepositoryInfo>,
+        branch: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let octocrab = self.octocrab()?;
 
-        let octocrab = octocrab::Octocrab::builder()
-            .personal_token(token.clone())
-            .build()
-            .context("Failed to create octocrab instance")
+        let pr = octocrab
+            .pulls(&repository.owner, &repository.repo)
+            .create(title, head(repository, fork, branch), base)
+            .body(body)
+            .send()
+            .await
+            .context("Failed to create pull request")
+            .suggestion("Please check your GitHub personal access token")?;
+
+        to_pull_request(pr)
+    }
+
+    async fn find_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        fork: Option<&RepositoryInfo>,
+        branch: &str,
+    ) -> Result<Option<PullRequest>> {
+        let octocrab = self.octocrab()?;
+
+        let page = octocrab
+            .pulls(&repository.owner, &repository.repo)
+            .list()
+            .head(head(repository, fork, branch))
+            .state(octocrab::params::State::Open)
+            .send()
+            .await
+            .context("Failed to look up pull requests")
             .suggestion("Please check your GitHub personal access token")?;
 
-        let git_client = git_client::get_git_client()?;
+        page.items
+            .into_iter()
+            .next()
+            .map(to_pull_request)
+            .transpose()
+    }
 
-        let commit_title = git_client.get_current_commit_title()?;
-        let title = ask_for_pr_title(&commit_title)?;
+    async fn get_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+    ) -> Result<PullRequest> {
+        let pr = self
+            .octocrab()?
+            .pulls(&repository.owner, &repository.repo)
+            .get(number)
+            .await
+            .context("Failed to get pull request")
+            .suggestion("Please check your GitHub personal access token")?;
 
-        let commit_body = git_client.get_current_commit_body()?;
-        let body = ask_for_pr_body(&commit_body)?;
+        to_pull_request(pr)
+    }
+
+    async fn update_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let octocrab = self.octocrab()?;
 
         let pr = octocrab
-            .pulls(owner, repo)
-            .create(title, branch, trunk)
+            .pulls(&repository.owner, &repository.repo)
+            .update(number)
+            .base(base)
+            .title(title)
             .body(body)
             .send()
             .await
-            .context("Failed to create pull request")
+            .context("Failed to update pull request")
             .suggestion("Please check your GitHub personal access token")?;
 
-        let comment = json!({
-            "body": "Automatically created using [gi](https://github.com/costinsin/gi).",
-        });
-        let route = format!("/repos/{}/{}/issues/{}/comments", owner, repo, pr.number);
-
-        let _response: octocrab::models::issues::Comment =
-            octocrab.post(route, Some(&comment)).await?;
+        to_pull_request(pr)
+    }
+}
 
-        let pr_url = pr.html_url.ok_or_eyre("Failed to get pull request URL")?;
-        println!(
-            "\nPull request created successfully! You can check it out at:\n{}",
-            pr_url
-        );
+impl GitHub {
+    fn octocrab(&self) -> Result<octocrab::Octocrab> {
+        let token = self.get_token()?;
+        let mut builder = octocrab::Octocrab::builder().personal_token(token);
+
+        // GitHub Enterprise Server serves its API under /api/v3 instead of a separate host
+        let api_url = match &self.api_url {
+            Some(api_url) => Some(api_url.clone()),
+            None if self.host != GITHUB_HOST => Some(format!("{}/api/v3", self.web_url)),
+            None => None,
+        };
+        if let Some(api_url) = api_url {
+            builder = builder
+                .base_uri(api_url)
+                .context("Invalid GitHub API URL")
+                .suggestion("Check the API URL configured with `gi host`")?;
+        }
 
-        Ok(())
+        builder
+            .build()
+            .context("Failed to create octocrab instance")
+            .suggestion("Please check your GitHub personal access token")
     }
 }
+
+/// Formats the head of a pull request as `owner:branch`, which is how GitHub refers to branches of forks.
+fn head(repository: &RepositoryInfo, fork: Option<&RepositoryInfo>, branch: &str) -> String {
+    format!("{}:{}", fork.unwrap_or(repository).owner, branch)
+}
+
+fn to_pull_request(pr: octocrab::models::pulls::PullRequest) -> Result<PullRequest> {
+    Ok(PullRequest {
+        number: pr.number,
+        url: pr
+            .html_url
+            .ok_or_eyre("Failed to get pull request URL")?
+            .to_string(),
+        base: pr.base.ref_field,
+        title: pr.title.unwrap_or_default(),
+        body: pr.body.unwrap_or_default(),
+        state: match (pr.merged_at, pr.state) {
+            (Some(_), _) => PullRequestState::Merged,
+            (None, Some(IssueState::Closed)) => PullRequestState::Closed,
+            _ => PullRequestState::Open,
+        },
+    })
+}
@@ -0,0 +1,176 @@
+use async_trait::async_trait;
+use eyre::Result;
+use reqwest::{Client, Method, RequestBuilder};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{send_json, GitProvider, PullRequest, PullRequestState};
+use crate::git_client::RepositoryInfo;
+
+/// A GitLab instance, reached through its REST v4 API.
+#[derive(Debug, Clone)]
+pub struct GitLab {
+    /// The base URL of the API, e.g. `https://gitlab.com/api/v4`.
+    api_url: String,
+    /// The host of the instance, which tokens are stored under.
+    host: String,
+    /// The URL of the instance, used to point the user at its token settings.
+    web_url: String,
+}
+
+/// A merge request as returned by the GitLab API.
+#[derive(Deserialize, Debug)]
+struct MergeRequest {
+    iid: u64,
+    web_url: String,
+    target_branch: String,
+    title: String,
+    description: Option<String>,
+    /// One of `opened`, `closed`, `locked` or `merged`.
+    #[serde(default)]
+    state: String,
+}
+
+impl From<MergeRequest> for PullRequest {
+    fn from(mr: MergeRequest) -> Self {
+        Self {
+            number: mr.iid,
+            url: mr.web_url,
+            base: mr.target_branch,
+            title: mr.title,
+            body: mr.description.unwrap_or_default(),
+            state: match mr.state.as_str() {
+                "merged" => PullRequestState::Merged,
+                "closed" => PullRequestState::Closed,
+                _ => PullRequestState::Open,
+            },
+        }
+    }
+}
+
+impl GitLab {
+    /// Creates a GitLab provider for the instance hosting the specified repository.
+    pub fn new(repository: &RepositoryInfo) -> Result<Self> {
+        Ok(Self::with_api_url(
+            repository,
+            format!("{}/api/v4", repository.web_url),
+        ))
+    }
+
+    /// Creates a GitLab provider talking to the API at the specified URL.
+    pub fn with_api_url(repository: &RepositoryInfo, api_url: String) -> Self {
+        Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            host: repository.host.clone(),
+            web_url: repository.web_url.clone(),
+        }
+    }
+
+    fn request(
+        &self,
+        method: Method,
+        repository: &RepositoryInfo,
+        path: &str,
+    ) -> Result<RequestBuilder> {
+        let token = self.get_token()?;
+
+        // Projects are addressed by their URL-encoded full path, which includes nested groups
+        let project = format!("{}/{}", repository.owner, repository.repo).replace('/', "%2F");
+        let url = format!(
+            "{}/projects/{}/merge_requests{}",
+            self.api_url, project, path
+        );
+
+        Ok(Client::new()
+            .request(method, url)
+            .header("PRIVATE-TOKEN", token))
+    }
+}
+
+#[async_trait]
+impl GitProvider for GitLab {
+    fn token_key(&self) -> String {
+        self.host.clone()
+    }
+
+    fn token_prompt(&self) -> String {
+        format!(
+            "Set up your GitLab personal access token. You can generate one at:
+{}/-/user_settings/personal_access_tokens?name=gi&scopes=api\n",
+            self.web_url
+        )
+    }
+
+    fn pull_request_reference(&self, number: u64) -> String {
+        format!("!{number}")
+    }
+
+    async fn create_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        _fork: Option<&RepositoryInfo>,
+        branch: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let request = self.request(Method::POST, repository, "")?.json(&json!({
+            "source_branch": branch,
+            "target_branch": base,
+            "title": title,
+            "description": body,
+        }));
+
+        let mr: MergeRequest = send_json(request, "Failed to create merge request").await?;
+
+        Ok(mr.into())
+    }
+
+    async fn find_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        _fork: Option<&RepositoryInfo>,
+        branch: &str,
+    ) -> Result<Option<PullRequest>> {
+        let request = self
+            .request(Method::GET, repository, "")?
+            .query(&[("source_branch", branch), ("state", "opened")]);
+
+        let mrs: Vec<MergeRequest> = send_json(request, "Failed to look up merge requests").await?;
+
+        Ok(mrs.into_iter().next().map(PullRequest::from))
+    }
+
+    async fn get_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+    ) -> Result<PullRequest> {
+        let request = self.request(Method::GET, repository, &format!("/{number}"))?;
+
+        let mr: MergeRequest = send_json(request, "Failed to get merge request").await?;
+
+        Ok(mr.into())
+    }
+
+    async fn update_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let request = self
+            .request(Method::PUT, repository, &format!("/{number}"))?
+            .json(&json!({
+                "target_branch": base,
+                "title": title,
+                "description": body,
+            }));
+
+        let mr: MergeRequest = send_json(request, "Failed to update merge request").await?;
+
+        Ok(mr.into())
+    }
+}
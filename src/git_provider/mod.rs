@@ -1,26 +1,60 @@
-#![allow(async_fn_in_trait)]
-
+use async_trait::async_trait;
+use clap::ValueEnum;
 use core::fmt;
 use dialoguer::{theme::ColorfulTheme, FuzzySelect};
-use std::path::PathBuf;
+use reqwest::RequestBuilder;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    fs::{self, create_dir_all, File},
+    os::unix::fs::PermissionsExt,
+    path::Path,
+};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 use color_eyre::Section;
 use eyre::{Context, OptionExt, Result};
 
+use crate::{
+    cli::interactive::ensure_interactive,
+    git_client::RepositoryInfo,
+    user::settings::{get_config_dir, get_user_settings, UserSettings},
+};
+
+pub mod azure_devops;
+pub mod bitbucket_cloud;
+pub mod bitbucket_server;
+pub mod gitea;
 pub mod github;
+pub mod gitlab;
 
 /// Enum representing the supported Git providers.
-#[derive(EnumIter)]
+#[derive(EnumIter, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
 pub enum SupportedProviders {
+    #[value(name = "github")]
     GitHub,
+    #[value(name = "gitlab")]
+    GitLab,
+    #[value(name = "gitea")]
+    Gitea,
+    #[value(name = "bitbucket-cloud")]
+    BitbucketCloud,
+    #[value(name = "bitbucket-server")]
+    BitbucketServer,
+    #[value(name = "azure-devops")]
+    AzureDevOps,
 }
 
 impl fmt::Display for SupportedProviders {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             SupportedProviders::GitHub => write!(f, "GitHub"),
+            SupportedProviders::GitLab => write!(f, "GitLab"),
+            SupportedProviders::Gitea => write!(f, "Gitea"),
+            SupportedProviders::BitbucketCloud => write!(f, "Bitbucket Cloud"),
+            SupportedProviders::BitbucketServer => write!(f, "Bitbucket Server"),
+            SupportedProviders::AzureDevOps => write!(f, "Azure DevOps"),
         }
     }
 }
@@ -47,6 +81,17 @@ impl SupportedProviders {
 pub fn get_provider_enum(provider: &str) -> Result<SupportedProviders> {
     match provider {
         p if p.starts_with("github") => Ok(SupportedProviders::GitHub),
+        p if p.starts_with("gitlab") => Ok(SupportedProviders::GitLab),
+        p if ["gitea", "forgejo", "codeberg"]
+            .iter()
+            .any(|prefix| p.starts_with(prefix)) =>
+        {
+            Ok(SupportedProviders::Gitea)
+        }
+        p if p.starts_with("bitbucket.org") => Ok(SupportedProviders::BitbucketCloud),
+        p if p.ends_with("dev.azure.com") || p.ends_with("visualstudio.com") => {
+            Ok(SupportedProviders::AzureDevOps)
+        }
         p => Err(eyre::eyre!("Unsupported provider {}", p)).suggestion(format!(
             "Supported providers: {}.\n
             Add a remote that uses one of the currently supported providers.",
@@ -55,6 +100,27 @@ pub fn get_provider_enum(provider: &str) -> Result<SupportedProviders> {
     }
 }
 
+/// Resolves the provider hosting repositories on the specified host, preferring the host mappings
+/// configured with `gi host` over recognising well-known host names.
+///
+/// # Arguments
+///
+/// * `host` - The host of the remote repository.
+/// * `user_settings` - The user settings holding the configured host mappings.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the provider if successful, or an error if the host isn't recognised.
+pub fn resolve_provider(host: &str, user_settings: &UserSettings) -> Result<SupportedProviders> {
+    if let Some(settings) = user_settings.get_host(host) {
+        return Ok(settings.provider);
+    }
+
+    get_provider_enum(host).suggestion(format!(
+        "Run `gi host {host} --provider <provider>` if {host} hosts one of the supported providers."
+    ))
+}
+
 /// Asks the user to input the title for a pull request.
 ///
 /// # Arguments
@@ -64,7 +130,7 @@ pub fn get_provider_enum(provider: &str) -> Result<SupportedProviders> {
 /// # Returns
 ///
 /// Returns a `Result` containing the user-provided title as a `String` if successful, or an error if the input prompt fails.
-pub fn ask_for_pr_title(commit_title: &String) -> Result<String> {
+pub fn ask_for_pr_title(commit_title: &str) -> Result<String> {
     let title = dialoguer::Input::<String>::with_theme(&ColorfulTheme::default())
         .with_prompt("Set the PR title:")
         .default(commit_title.to_owned())
@@ -82,7 +148,7 @@ pub fn ask_for_pr_title(commit_title: &String) -> Result<String> {
 /// # Returns
 ///
 /// Returns a `Result` containing the user-provided body as a `String` if successful, or an error if the input prompt fails.
-pub fn ask_for_pr_body(commit_body: &String) -> Result<String> {
+pub fn ask_for_pr_body(commit_body: &str) -> Result<String> {
     let options = vec![
         "Use commit body",
         "Edit commit body",
@@ -112,8 +178,129 @@ pub fn ask_for_pr_body(commit_body: &String) -> Result<String> {
     Ok(body)
 }
 
+/// A pull request as reported by a Git provider.
+#[derive(Debug, Clone)]
+pub struct PullRequest {
+    pub number: u64,
+    pub url: String,
+    pub base: String,
+    pub title: String,
+    pub body: String,
+    pub state: PullRequestState,
+}
+
+/// Whether a pull request is still open, and how it was closed otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PullRequestState {
+    Open,
+    Merged,
+    /// Closed without being merged, e.g. declined or abandoned.
+    Closed,
+}
+
+impl fmt::Display for PullRequestState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PullRequestState::Open => write!(f, "open"),
+            PullRequestState::Merged => write!(f, "merged"),
+            PullRequestState::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+/// A pull request listed in the stack section of a pull request description.
+#[derive(Debug, Clone)]
+pub struct StackEntry {
+    /// How many branches the pull request's branch sits above the bottom of the stack.
+    pub depth: usize,
+    pub pull_request: PullRequest,
+}
+
+/// Marks the start of the stack section gi maintains in pull request descriptions.
+pub const STACK_SECTION_START: &str = "<!-- gi-stack-start -->";
+/// Marks the end of the stack section gi maintains in pull request descriptions.
+pub const STACK_SECTION_END: &str = "<!-- gi-stack-end -->";
+
+/// Replaces the stack section of a pull request description, appending it if there is none yet.
+///
+/// # Arguments
+///
+/// * `body` - The current description of the pull request.
+/// * `section` - The new stack section, including its start and end markers.
+///
+/// # Returns
+///
+/// Returns the description with the new stack section.
+pub fn replace_stack_section(body: &str, section: &str) -> String {
+    if let Some(start) = body.find(STACK_SECTION_START) {
+        if let Some(end) = body[start..].find(STACK_SECTION_END) {
+            let end = start + end + STACK_SECTION_END.len();
+            return format!("{}{}{}", &body[..start], section, &body[end..]);
+        }
+    }
+
+    if body.trim().is_empty() {
+        return section.to_string();
+    }
+
+    format!("{}\n\n{}", body.trim_end(), section)
+}
+
+/// Sends a request to a provider's REST API and parses its JSON response.
+///
+/// # Arguments
+///
+/// * `request` - The request to send, including its authentication header.
+/// * `error` - The error message to report if the request fails.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the parsed response, or an error if the request fails or returns an error status.
+pub(crate) async fn send_json<T: DeserializeOwned>(
+    request: RequestBuilder,
+    error: &'static str,
+) -> Result<T> {
+    let response = request
+        .send()
+        .await
+        .context(error)?
+        .error_for_status()
+        .context(error)
+        .suggestion("Please check your personal access token")?;
+
+    response
+        .json::<T>()
+        .await
+        .context("Failed to parse the provider response")
+}
+
+/// Authenticates a request with the stored credentials.
+///
+/// Credentials in the `<username>:<password>` form are sent using basic authentication, anything
+/// else is sent as a bearer token.
+pub(crate) fn with_credentials(request: RequestBuilder, credentials: &str) -> RequestBuilder {
+    match credentials.split_once(':') {
+        Some((username, password)) => request.basic_auth(username, Some(password)),
+        None => request.bearer_auth(credentials),
+    }
+}
+
 /// Trait representing a Git provider.
-pub trait GitProvider {
+#[async_trait]
+pub trait GitProvider: Send + Sync {
+    /// Returns the key the authentication token is stored under in the token file, which is the
+    /// host of the provider so that every instance gets its own token.
+    fn token_key(&self) -> String;
+
+    /// Returns the key older versions of gi stored the authentication token under, if any.
+    fn legacy_token_key(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns the prompt shown when asking the user for an authentication token.
+    fn token_prompt(&self) -> String;
+
     /// Sets the authentication token.
     ///
     /// # Arguments
@@ -123,47 +310,266 @@ pub trait GitProvider {
     /// # Returns
     ///
     /// Returns a `Result` containing the token as a `String` if successful, or an error if the token cannot be set.
-    fn ask_for_token(&self, path: &PathBuf) -> Result<String>;
+    fn ask_for_token(&self, path: &Path) -> Result<String> {
+        ensure_interactive(
+            &format!("an access token for {}", self.token_key()),
+            &format!(
+                "Store the token in {} under the \"{}\" key.",
+                path.display(),
+                self.token_key()
+            ),
+        )?;
+
+        let token = dialoguer::Input::<String>::with_theme(&ColorfulTheme::default())
+            .with_prompt(self.token_prompt())
+            .interact()?;
+
+        // Keep the tokens of the other providers stored in the same file
+        let mut data = fs::read_to_string(path)
+            .ok()
+            .and_then(|data| serde_json::from_str::<serde_json::Value>(&data).ok())
+            .filter(|value| value.is_object())
+            .unwrap_or_else(|| json!({}));
+        data[self.token_key()] = json!(token);
+
+        fs::write(path, data.to_string())
+            .context("Failed to write to token file")
+            .suggestion("Check if you have write permissions")?;
+
+        Ok(token)
+    }
 
     /// Retrieves the authentication token.
     ///
     /// # Returns
     ///
     /// Returns a `Result` containing the token as a `String` if successful, or an error if the token cannot be retrieved.
-    fn get_token(&self) -> Result<String>;
+    fn get_token(&self) -> Result<String> {
+        let config_dir = get_config_dir()?;
+        let token_file = config_dir.join("token");
+
+        if !token_file.exists() {
+            create_dir_all(&config_dir)
+                .context("Failed to create gi config directory")
+                .suggestion("Check if you have write permissions to the .config directory")?;
+
+            File::create_new(&token_file)
+                .context("Failed to create token file")
+                .suggestion("Check if you have write permissions to the .config/gi directory")?;
+
+            /* TODO: Implement setting file permissions on Windows
+            At the time of writing this code, fs::Permissions::from_mode only
+            works on Unix systems. In the future, we'll find a way to perform
+            the same operation on Windows. */
+
+            let permissions = fs::Permissions::from_mode(0o600);
+            fs::set_permissions(&token_file, permissions)
+                .context("Failed to set permissions on token file")?;
+
+            return self.ask_for_token(&token_file);
+        }
+
+        let data = fs::read_to_string(&token_file)
+            .context("Failed to read file")
+            .suggestion("Check if you have read permissions to the token file")?;
+        let deserealized = serde_json::from_str::<serde_json::Value>(&data);
+
+        match deserealized {
+            Ok(value) => {
+                let token = value[self.token_key()].as_str().or_else(|| {
+                    self.legacy_token_key()
+                        .and_then(|legacy_key| value[legacy_key].as_str())
+                });
+
+                match token {
+                    Some(token) => Ok(token.to_string()),
+                    None => self.ask_for_token(&token_file),
+                }
+            }
+            Err(_) => self.ask_for_token(&token_file),
+        }
+    }
+
+    /// Returns whether pull requests can be opened from a branch of a fork.
+    fn supports_forks(&self) -> bool {
+        false
+    }
 
     /// Creates a pull request.
     ///
     /// # Arguments
     ///
-    /// * `owner` - The owner of the repository.
-    /// * `repo` - The name of the repository.
+    /// * `repository` - The remote repository.
+    /// * `fork` - The fork the branch was pushed to, or `None` if it was pushed to the repository itself.
     /// * `branch` - The name of the branch to create the pull request from.
-    /// * `trunk` - The name of the trunk branch to create the pull request against.
+    /// * `base` - The name of the branch to create the pull request against.
+    /// * `title` - The title of the pull request.
+    /// * `body` - The description of the pull request.
     ///
     /// # Returns
     ///
-    /// Returns a `Result` containing `()` if successful, or an error if the pull request cannot be created.
+    /// Returns a `Result` containing the created pull request if successful, or an error if the pull request cannot be created.
     async fn create_pull_request(
         &self,
-        owner: &String,
-        repo: &String,
-        branch: &String,
-        trunk: &String,
-    ) -> eyre::Result<()>;
+        repository: &RepositoryInfo,
+        fork: Option<&RepositoryInfo>,
+        branch: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> eyre::Result<PullRequest>;
+
+    /// Looks up the open pull request created from a branch.
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The remote repository.
+    /// * `fork` - The fork the branch was pushed to, or `None` if it was pushed to the repository itself.
+    /// * `branch` - The name of the branch the pull request was created from.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the pull request, or `None` if the branch has no open pull request.
+    async fn find_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        fork: Option<&RepositoryInfo>,
+        branch: &str,
+    ) -> eyre::Result<Option<PullRequest>>;
+
+    /// Retrieves a pull request by its number.
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The remote repository.
+    /// * `number` - The number of the pull request.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the pull request if successful, or an error if it cannot be retrieved.
+    async fn get_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+    ) -> eyre::Result<PullRequest>;
+
+    /// Updates the base, title and description of an existing pull request.
+    ///
+    /// # Arguments
+    ///
+    /// * `repository` - The remote repository.
+    /// * `number` - The number of the pull request.
+    /// * `base` - The name of the branch the pull request should be merged into.
+    /// * `title` - The new title of the pull request.
+    /// * `body` - The new description of the pull request.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the updated pull request if successful, or an error if the pull request cannot be updated.
+    async fn update_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        number: u64,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> eyre::Result<PullRequest>;
+
+    /// Formats the reference to a pull request the way the provider links it in descriptions.
+    fn pull_request_reference(&self, number: u64) -> String {
+        format!("#{number}")
+    }
+
+    /// Renders the section listing every pull request of a stack, to be placed in the description of each of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - The name of the branch the stack is based on.
+    /// * `entries` - The pull requests of the stack, parents before children.
+    /// * `current` - The number of the pull request whose description the section is rendered for.
+    ///
+    /// # Returns
+    ///
+    /// Returns the section, including its start and end markers.
+    fn render_stack_section(&self, base: &str, entries: &[StackEntry], current: u64) -> String {
+        let mut lines = vec![
+            STACK_SECTION_START.to_string(),
+            "---".to_string(),
+            "**Stack**".to_string(),
+            String::new(),
+            format!("* `{base}`"),
+        ];
+
+        for entry in entries {
+            let pr = &entry.pull_request;
+            let marker = if pr.number == current { " 👈" } else { "" };
+
+            lines.push(format!(
+                "{}* [{} {}]({}){}",
+                "  ".repeat(entry.depth + 1),
+                self.pull_request_reference(pr.number),
+                pr.title,
+                pr.url,
+                marker
+            ));
+        }
+
+        lines.push(String::new());
+        lines.push(
+            "<sub>Automatically maintained using [gi](https://github.com/costinsin/gi).</sub>"
+                .to_string(),
+        );
+        lines.push(STACK_SECTION_END.to_string());
+
+        lines.join("\n")
+    }
 }
 
-/// Creates an instance of the Git provider based on the specified `SupportedProviders` enum variant.
+/// A function creating the provider hosting a repository, like `provider_factory`.
+pub type ProviderFactory = dyn Fn(&RepositoryInfo) -> Result<Box<dyn GitProvider>>;
+
+/// Creates an instance of the Git provider hosting the specified repository.
 ///
 /// # Arguments
 ///
-/// * `provider` - The `SupportedProviders` enum variant representing the desired Git provider.
+/// * `repository` - The remote repository, including its provider and host.
 ///
 /// # Returns
 ///
 /// Returns a `Result` containing a boxed trait object implementing the `GitProvider` trait if successful, or an error if the provider fails to initialize.
-pub fn provider_factory(provider: &SupportedProviders) -> Result<Box<impl GitProvider>> {
-    match provider {
-        SupportedProviders::GitHub => Ok(Box::new(github::GitHub::new()?)),
+pub fn provider_factory(repository: &RepositoryInfo) -> Result<Box<dyn GitProvider>> {
+    let host = repository.host.as_str();
+    let api_url = get_user_settings()?
+        .get_host(host)
+        .and_then(|settings| settings.api_url.clone());
+
+    match (repository.provider, api_url) {
+        (SupportedProviders::GitHub, api_url) => {
+            Ok(Box::new(github::GitHub::new(repository, api_url)?))
+        }
+        (SupportedProviders::GitLab, Some(api_url)) => {
+            Ok(Box::new(gitlab::GitLab::with_api_url(repository, api_url)))
+        }
+        (SupportedProviders::GitLab, None) => Ok(Box::new(gitlab::GitLab::new(repository)?)),
+        (SupportedProviders::Gitea, Some(api_url)) => {
+            Ok(Box::new(gitea::Gitea::with_api_url(repository, api_url)))
+        }
+        (SupportedProviders::Gitea, None) => Ok(Box::new(gitea::Gitea::new(repository)?)),
+        (SupportedProviders::BitbucketCloud, Some(api_url)) => Ok(Box::new(
+            bitbucket_cloud::BitbucketCloud::with_api_url(api_url),
+        )),
+        (SupportedProviders::BitbucketCloud, None) => {
+            Ok(Box::new(bitbucket_cloud::BitbucketCloud::new()?))
+        }
+        (SupportedProviders::BitbucketServer, Some(api_url)) => Ok(Box::new(
+            bitbucket_server::BitbucketServer::with_api_url(repository, api_url),
+        )),
+        (SupportedProviders::BitbucketServer, None) => Ok(Box::new(
+            bitbucket_server::BitbucketServer::new(repository)?,
+        )),
+        (SupportedProviders::AzureDevOps, Some(api_url)) => {
+            Ok(Box::new(azure_devops::AzureDevOps::with_api_url(api_url)))
+        }
+        (SupportedProviders::AzureDevOps, None) => Ok(Box::new(azure_devops::AzureDevOps::new()?)),
     }
 }
@@ -7,6 +7,10 @@ pub mod commands;
 pub mod git_client;
 pub mod git_provider;
 pub mod project;
+pub mod stack;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod user;
 
 pub trait IssueError<T> {
     fn to_issue_error(self, error: &str) -> Result<T>;
@@ -1,20 +1,153 @@
+use std::io::IsTerminal;
+
 use clap::Parser;
 use eyre::Result;
 use gi::{
     cli::args::{Args, Commands},
-    commands::create::create,
-    commands::submit::submit,
+    cli::interactive::set_interactive,
+    cli::output::{emit_error, install_error_hooks, is_json, set_json},
+    commands::create::{create, CommitOptions},
+    commands::fold::fold,
+    commands::host::host,
+    commands::log::log,
+    commands::modify::modify,
+    commands::move_branch::move_branch,
+    commands::navigate::{bottom, down, top, up},
+    commands::operation::{abort_operation, continue_operation},
+    commands::restack::restack,
+    commands::split::{split, SplitOptions},
+    commands::squash::squash,
+    commands::submit::{read_body_file, submit, SubmitOptions},
+    commands::sync::{sync, SyncOptions},
+    commands::track::{track, untrack},
+    commands::StackOptions,
+    git_client::get_git_client,
+    git_provider::provider_factory,
+    project::settings::get_project_settings,
 };
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
-    color_eyre::install()?;
+    set_json(args.json);
+    install_error_hooks()?;
+    set_interactive(!args.no_interactive && std::io::stdin().is_terminal());
+
+    match run(args).await {
+        // Errors are part of the JSON output, so that they can be told apart from the results
+        Err(error) if is_json() => {
+            emit_error(&error);
+            std::process::exit(1);
+        }
+        result => result,
+    }
+}
+
+async fn run(args: Args) -> Result<()> {
+    if let Some(trunk) = &args.trunk {
+        get_project_settings()?.set_trunk(trunk)?;
+    }
 
     match args.command {
-        Commands::Create => create()?,
-        Commands::Submit => submit().await?,
+        Commands::Create { all, message } => create(
+            get_git_client()?.as_ref(),
+            &CommitOptions::new(all, message),
+        )?,
+        Commands::Submit {
+            stack,
+            title,
+            body,
+            body_file,
+        } => {
+            let body = match body_file {
+                Some(path) => Some(read_body_file(&path)?),
+                None => body,
+            };
+            let options = SubmitOptions {
+                title,
+                body,
+                ..SubmitOptions::from_project_settings(stack)?
+            };
+            submit(get_git_client()?.as_ref(), &provider_factory, &options).await?
+        }
+        Commands::Log => log(
+            get_git_client()?.as_ref(),
+            &StackOptions::from_project_settings()?,
+        )?,
+        Commands::Restack => restack(
+            get_git_client()?.as_ref(),
+            &StackOptions::from_project_settings()?,
+        )?,
+        Commands::Continue => continue_operation(get_git_client()?.as_ref())?,
+        Commands::Abort => abort_operation(get_git_client()?.as_ref())?,
+        Commands::Modify {
+            commit,
+            all,
+            message,
+        } => modify(
+            get_git_client()?.as_ref(),
+            commit,
+            &CommitOptions::new(all, message),
+            &StackOptions::from_project_settings()?,
+        )?,
+        Commands::Up { steps } => up(
+            get_git_client()?.as_ref(),
+            steps,
+            &StackOptions::from_project_settings()?,
+        )?,
+        Commands::Down { steps } => down(
+            get_git_client()?.as_ref(),
+            steps,
+            &StackOptions::from_project_settings()?,
+        )?,
+        Commands::Top => top(
+            get_git_client()?.as_ref(),
+            &StackOptions::from_project_settings()?,
+        )?,
+        Commands::Bottom => bottom(
+            get_git_client()?.as_ref(),
+            &StackOptions::from_project_settings()?,
+        )?,
+        Commands::Move { onto, descendants } => move_branch(
+            get_git_client()?.as_ref(),
+            &onto,
+            descendants,
+            &StackOptions::from_project_settings()?,
+        )?,
+        Commands::Split { by } => split(
+            get_git_client()?.as_ref(),
+            &SplitOptions::from_project_settings(by)?,
+        )?,
+        Commands::Fold => fold(
+            get_git_client()?.as_ref(),
+            &StackOptions::from_project_settings()?,
+        )?,
+        Commands::Squash { message } => squash(
+            get_git_client()?.as_ref(),
+            &CommitOptions::new(false, message),
+            &StackOptions::from_project_settings()?,
+        )?,
+        Commands::Track { branch, parent } => track(
+            get_git_client()?.as_ref(),
+            branch.as_deref(),
+            parent.as_deref(),
+            &StackOptions::from_project_settings()?,
+        )?,
+        Commands::Untrack { branch } => untrack(
+            get_git_client()?.as_ref(),
+            branch.as_deref(),
+            &StackOptions::from_project_settings()?,
+        )?,
+        Commands::Sync { force } => {
+            let options = SyncOptions::from_project_settings(force)?;
+            sync(get_git_client()?.as_ref(), &provider_factory, &options).await?
+        }
+        Commands::Host {
+            host: name,
+            provider,
+            api_url,
+        } => host(&name, provider, api_url)?,
     }
 
     Ok(())
@@ -1,25 +1,44 @@
 use color_eyre::Section;
-use dialoguer::theme::ColorfulTheme;
+use dialoguer::{theme::ColorfulTheme, FuzzySelect};
 use eyre::{eyre, Context, Ok, Result};
-use once_cell::sync::Lazy;
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 use std::{
-    path::Path,
+    path::{Path, PathBuf},
     sync::{Mutex, MutexGuard},
 };
 
-use crate::{git_client::get_git_client, IssueError};
+use crate::{
+    cli::interactive::ensure_interactive,
+    git_client::{get_git_client, GitClient},
+    IssueError,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ProjectSettings {
     trunk: Option<String>,
+    /// The remote branches are pushed to, e.g. a fork.
+    #[serde(
+        rename = "pushRemote",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    push_remote: Option<String>,
+    /// The remote of the repository pull requests are opened against.
+    #[serde(
+        rename = "targetRemote",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    target_remote: Option<String>,
 }
 
-static PROJECT_SETTINGS: Lazy<Mutex<ProjectSettings>> =
-    Lazy::new(|| Mutex::new(ProjectSettings::load().unwrap()));
+// Like the user settings, loading is retried until it succeeds so that errors reach the user
+static PROJECT_SETTINGS: OnceCell<Mutex<ProjectSettings>> = OnceCell::new();
 
 pub fn get_project_settings() -> Result<MutexGuard<'static, ProjectSettings>> {
     let guard = PROJECT_SETTINGS
+        .get_or_try_init(|| ProjectSettings::load().map(Mutex::new))?
         .lock()
         .to_issue_error("Failed to get project settings lock")?;
 
@@ -28,14 +47,8 @@ pub fn get_project_settings() -> Result<MutexGuard<'static, ProjectSettings>> {
 
 impl ProjectSettings {
     fn load() -> Result<Self> {
-        let Some(repository_root) = get_git_client()?.get_repository_root() else {
-            return Err(eyre!("You are not inside a git repository.").suggestion(
-                "Run `gi` inside a git repository or run `git init` to create a new one.",
-            ));
-        };
-
         // Check if the project has a .gi_project_config file, if not, return a default ProjectSettings
-        let config_path = Path::new(&repository_root).join(".git/.gi_project_config");
+        let config_path = project_config_path(&*get_git_client()?)?;
         if !config_path.exists() {
             return Ok(Self::default());
         }
@@ -55,8 +68,8 @@ impl ProjectSettings {
         Ok(settings?)
     }
 
-    pub fn set_trunk(&mut self, trunk: &String) -> Result<()> {
-        self.trunk = Some(trunk.clone());
+    pub fn set_trunk(&mut self, trunk: &str) -> Result<()> {
+        self.trunk = Some(trunk.to_owned());
         self.save()?;
 
         Ok(())
@@ -76,27 +89,104 @@ impl ProjectSettings {
         Ok(trunk)
     }
 
-    fn save(&self) -> Result<()> {
-        let Some(repository_root) = get_git_client()?.get_repository_root() else {
-            return Err(eyre!("You are not inside a git repository.").suggestion(
-                "Run `gi` inside a git repository or run `git init` to create a new one.",
-            ));
-        };
+    pub fn set_push_remote(&mut self, remote: &str) -> Result<()> {
+        self.push_remote = Some(remote.to_owned());
+        self.save()?;
 
+        Ok(())
+    }
+
+    /// Returns the remote branches are pushed to, asking the user to pick one on first use if the
+    /// repository has several.
+    pub fn get_push_remote(&mut self) -> Result<String> {
+        if let Some(remote) = self.push_remote.to_owned() {
+            return Ok(remote);
+        }
+
+        // The choice is only remembered once there is one to make, so that adding a remote later prompts again
+        let remotes = get_git_client()?.list_remotes()?;
+        if let [remote] = remotes.as_slice() {
+            return Ok(remote.to_owned());
+        }
+
+        let remote = ask_for_remote(
+            "Which remote should branches be pushed to?",
+            &remotes,
+            "origin",
+        )?;
+        self.set_push_remote(&remote)?;
+
+        Ok(remote)
+    }
+
+    pub fn set_target_remote(&mut self, remote: &str) -> Result<()> {
+        self.target_remote = Some(remote.to_owned());
+        self.save()?;
+
+        Ok(())
+    }
+
+    /// Returns the remote of the repository pull requests are opened against, asking the user to
+    /// pick one on first use if the repository has several.
+    pub fn get_target_remote(&mut self) -> Result<String> {
+        if let Some(remote) = self.target_remote.to_owned() {
+            return Ok(remote);
+        }
+
+        let remotes = get_git_client()?.list_remotes()?;
+        if let [remote] = remotes.as_slice() {
+            return Ok(remote.to_owned());
+        }
+
+        // Forks conventionally name the repository they were forked from `upstream`
+        let remote = ask_for_remote(
+            "Which remote should pull requests be opened against?",
+            &remotes,
+            "upstream",
+        )?;
+        self.set_target_remote(&remote)?;
+
+        Ok(remote)
+    }
+
+    fn save(&self) -> Result<()> {
+        let config_path = project_config_path(&*get_git_client()?)?;
         let json_settings = serde_json::to_string(self)?;
 
-        std::fs::write(
-            Path::new(&repository_root).join(".git/.gi_project_config"),
-            json_settings,
-        )
-        .context("Failed to save project settings")
-        .suggestion("Check if you have write permissions to the .git directory.")?;
+        std::fs::write(config_path, json_settings)
+            .context("Failed to save project settings")
+            .suggestion("Check if you have write permissions to the .git directory.")?;
 
         Ok(())
     }
 }
 
+/// Returns the path of the file holding the settings of the repository.
+///
+/// # Arguments
+///
+/// * `git_client` - The git client of the repository.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the path of `.gi_project_config` in the git directory shared by
+/// the linked worktrees of the repository, or an error if not inside a git repository.
+pub fn project_config_path(git_client: &dyn GitClient) -> Result<PathBuf> {
+    let Some(git_dir) = git_client.get_git_common_dir() else {
+        return Err(eyre!("You are not inside a git repository.").suggestion(
+            "Run `gi` inside a git repository or run `git init` to create a new one.",
+        ));
+    };
+
+    Ok(Path::new(&git_dir).join(".gi_project_config"))
+}
+
 pub fn ask_for_trunk() -> Result<String> {
+    ensure_interactive(
+        "the name of the trunk branch",
+        "Pass it with `--trunk`, e.g. `gi --trunk main log`.",
+    )?;
+
     let trunk = dialoguer::Input::<String>::with_theme(&ColorfulTheme::default())
         .with_prompt("What is the name of the trunk branch?")
         .default("main".to_string())
@@ -104,3 +194,36 @@ pub fn ask_for_trunk() -> Result<String> {
 
     Ok(trunk)
 }
+
+/// Asks the user to pick one of the remotes of the repository.
+///
+/// # Arguments
+///
+/// * `prompt` - The question to ask the user.
+/// * `remotes` - The names of the remotes of the repository.
+/// * `default` - The name of the remote to preselect, if it exists.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the name of the chosen remote, or an error if the repository has no remotes.
+pub fn ask_for_remote(prompt: &str, remotes: &[String], default: &str) -> Result<String> {
+    if remotes.is_empty() {
+        return Err(eyre!("The repository has no remotes."))
+            .suggestion("Add one with `git remote add origin <url>`.");
+    }
+    ensure_interactive(
+        "to know which of the remotes of the repository to use",
+        &format!(
+            "Set `pushRemote` and `targetRemote` in {}.",
+            project_config_path(&*get_git_client()?)?.display()
+        ),
+    )?;
+
+    let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(remotes)
+        .default(remotes.iter().position(|r| r == default).unwrap_or(0))
+        .interact()?;
+
+    Ok(remotes[selection].to_owned())
+}
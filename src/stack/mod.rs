@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use eyre::Result;
+
+pub mod operation;
+
+use crate::git_client::{
+    metadata::{create_branch_metadata, get_all_branch_metadata, BranchMetadata},
+    GitClient, RebaseStatus,
+};
+
+/// The parent/child graph of every tracked branch, as recorded in `refs/branch-metadata/*`.
+pub struct StackGraph {
+    trunk: String,
+    metadata: BTreeMap<String, BranchMetadata>,
+}
+
+impl StackGraph {
+    /// Builds the stack graph from the metadata refs of the repository.
+    ///
+    /// # Arguments
+    ///
+    /// * `git_client` - The git client used to read the metadata refs.
+    /// * `trunk` - The name of the trunk branch every stack is rooted at.
+    pub fn load(git_client: &dyn GitClient, trunk: String) -> Result<Self> {
+        Ok(Self::new(trunk, get_all_branch_metadata(git_client)?))
+    }
+
+    pub fn new(trunk: String, metadata: BTreeMap<String, BranchMetadata>) -> Self {
+        Self { trunk, metadata }
+    }
+
+    pub fn trunk(&self) -> &str {
+        &self.trunk
+    }
+
+    pub fn is_tracked(&self, branch: &str) -> bool {
+        self.metadata.contains_key(branch)
+    }
+
+    pub fn metadata(&self, branch: &str) -> Option<&BranchMetadata> {
+        self.metadata.get(branch)
+    }
+
+    pub fn parent(&self, branch: &str) -> Option<&str> {
+        self.metadata
+            .get(branch)
+            .map(|metadata| metadata.parent_branch_name())
+    }
+
+    /// Returns the tracked branches whose parent is the specified branch, ordered by name.
+    pub fn children(&self, branch: &str) -> Vec<&str> {
+        self.metadata
+            .iter()
+            .filter(|(_, metadata)| metadata.parent_branch_name() == branch)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Returns the branches the stacks are rooted at: the trunk first, followed by every
+    /// untracked branch that tracked branches were created on top of.
+    pub fn roots(&self) -> Vec<&str> {
+        let orphan_parents = self
+            .metadata
+            .values()
+            .map(|metadata| metadata.parent_branch_name())
+            .filter(|parent| *parent != self.trunk && !self.is_tracked(parent))
+            .collect::<BTreeSet<&str>>();
+
+        std::iter::once(self.trunk.as_str())
+            .chain(orphan_parents)
+            .collect()
+    }
+
+    /// Returns every branch stacked on top of the specified one, parents before children.
+    pub fn descendants(&self, branch: &str) -> Vec<String> {
+        let mut descendants = Vec::new();
+        let mut visited = BTreeSet::from([branch.to_string()]);
+        self.collect_descendants(branch, &mut visited, &mut descendants);
+
+        descendants
+    }
+
+    /// Returns every tracked branch, parents before children.
+    pub fn topological_order(&self) -> Vec<String> {
+        let mut order = Vec::new();
+        let mut visited = BTreeSet::new();
+
+        for root in self.roots() {
+            visited.insert(root.to_string());
+            self.collect_descendants(root, &mut visited, &mut order);
+        }
+
+        order
+    }
+
+    /// Returns the tracked ancestors of the specified branch, from the bottom of the stack up to
+    /// (and excluding) the branch itself.
+    pub fn ancestors(&self, branch: &str) -> Vec<String> {
+        let mut ancestors = Vec::new();
+        let mut current = branch;
+
+        while let Some(parent) = self.parent(current) {
+            if !self.is_tracked(parent) || ancestors.iter().any(|a| a == parent) {
+                break;
+            }
+
+            ancestors.push(parent.to_string());
+            current = parent;
+        }
+
+        ancestors.reverse();
+        ancestors
+    }
+
+    fn collect_descendants(
+        &self,
+        branch: &str,
+        visited: &mut BTreeSet<String>,
+        descendants: &mut Vec<String>,
+    ) {
+        for child in self.children(branch) {
+            // Guard against cycles in hand-edited metadata
+            if !visited.insert(child.to_string()) {
+                continue;
+            }
+
+            descendants.push(child.to_string());
+            self.collect_descendants(child, visited, descendants);
+        }
+    }
+}
+
+/// Stacks the children of a branch on another parent, typically before the branch is deleted.
+///
+/// The children keep their recorded parent revision, so that restacking them replays only their
+/// own commits onto the new parent.
+///
+/// # Arguments
+///
+/// * `git_client` - The git client used to read and write the metadata refs.
+/// * `branch` - The branch whose children are moved.
+/// * `new_parent` - The branch the children are stacked on instead.
+///
+/// # Returns
+///
+/// A `Result` containing the names of the children that were moved.
+pub fn reparent_children(
+    git_client: &dyn GitClient,
+    branch: &str,
+    new_parent: &str,
+) -> Result<Vec<String>> {
+    let mut children = Vec::new();
+
+    for (child, mut metadata) in get_all_branch_metadata(git_client)? {
+        if metadata.parent_branch_name() != branch {
+            continue;
+        }
+
+        metadata.set_parent_branch_name(new_parent.to_string());
+        create_branch_metadata(git_client, child.clone(), &metadata)?;
+        children.push(child);
+    }
+
+    Ok(children)
+}
+
+/// Checks whether the parent of a tracked branch has moved past the revision the branch was
+/// stacked on.
+///
+/// Returns `false` when the parent branch no longer exists.
+pub fn needs_restack(git_client: &dyn GitClient, metadata: &BranchMetadata) -> bool {
+    match git_client.get_branch_revision(metadata.parent_branch_name()) {
+        Ok(revision) => revision != metadata.parent_branch_revision(),
+        Err(_) => false,
+    }
+}
+
+/// Rebases a tracked branch from the parent revision recorded in its metadata onto the current
+/// revision of its parent, then records the new parent revision.
+///
+/// Branches that already contain their parent's revision, and none of the commits of the parent
+/// revision they were stacked on besides it, only get their metadata updated.
+///
+/// # Returns
+///
+/// A `Result` containing `RebaseStatus::Conflict` if the rebase stopped on a conflict, in which
+/// case the metadata is left untouched.
+pub fn restack_branch(
+    git_client: &dyn GitClient,
+    branch: &str,
+    metadata: &BranchMetadata,
+) -> Result<RebaseStatus> {
+    let parent = metadata.parent_branch_name();
+    let parent_revision = git_client.get_branch_revision(parent)?;
+
+    // A branch moved onto an ancestor of its former parent still holds the commits of that parent
+    let recorded_revision = metadata.parent_branch_revision();
+    let contains_parent = git_client.is_ancestor(&parent_revision, branch)?
+        && (git_client.is_ancestor(recorded_revision, &parent_revision)?
+            || !git_client.is_ancestor(recorded_revision, branch)?);
+
+    if !contains_parent {
+        let status = git_client.rebase_onto(parent, recorded_revision, branch)?;
+
+        if let RebaseStatus::Conflict = status {
+            return Ok(status);
+        }
+    }
+
+    let mut metadata = metadata.clone();
+    metadata.set_parent_branch_revision(parent_revision);
+    create_branch_metadata(git_client, branch.to_string(), &metadata)?;
+
+    Ok(RebaseStatus::Success)
+}
@@ -0,0 +1,266 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::Section;
+use eyre::{eyre, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{needs_restack, restack_branch};
+use crate::{
+    cli::output,
+    git_client::{
+        metadata::{get_branch_metadata_oid, read_branch_metadata, BRANCH_METADATA_REF_PREFIX},
+        GitClient, RebaseStatus,
+    },
+};
+
+/// The state of a branch before a multi-branch operation started.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BranchSnapshot {
+    branch: String,
+    /// The revision of the branch, or `None` if it didn't exist.
+    revision: Option<String>,
+    /// The ID of the metadata blob of the branch, or `None` if it wasn't tracked.
+    metadata: Option<String>,
+}
+
+/// A multi-branch operation journaled in `.gi_operation` in the git directory of the worktree, so that it can be resumed with
+/// `gi continue` or rolled back with `gi abort` after stopping on a conflict.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Operation {
+    /// The name of the command that started the operation.
+    name: String,
+    /// The branch that was checked out when the operation started.
+    original_branch: Option<String>,
+    /// The state of every branch the operation may modify.
+    snapshots: Vec<BranchSnapshot>,
+    /// The branches left to restack, the first one being the one in progress.
+    pending: Vec<String>,
+    /// The branches restacked so far, including before the operation stopped on a conflict.
+    #[serde(default)]
+    restacked: Vec<String>,
+}
+
+impl Operation {
+    /// Starts a new operation, snapshotting the branches it may modify.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the command starting the operation.
+    /// * `affected` - Every branch whose ref or metadata the operation may modify.
+    /// * `pending` - The branches to restack, parents before children.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if another operation is already in progress.
+    pub fn begin(
+        git_client: &dyn GitClient,
+        name: &str,
+        affected: &[String],
+        pending: Vec<String>,
+    ) -> Result<Self> {
+        Self::ensure_none_in_progress(git_client)?;
+
+        let mut snapshots = Vec::<BranchSnapshot>::new();
+        for branch in affected.iter().chain(pending.iter()) {
+            if snapshots.iter().any(|s| &s.branch == branch) {
+                continue;
+            }
+
+            snapshots.push(BranchSnapshot {
+                branch: branch.clone(),
+                revision: git_client
+                    .get_branch_revision(&format!("refs/heads/{branch}"))
+                    .ok(),
+                metadata: get_branch_metadata_oid(git_client, branch),
+            });
+        }
+
+        let operation = Self {
+            name: name.to_string(),
+            original_branch: git_client.get_current_branch(),
+            snapshots,
+            pending,
+            restacked: Vec::new(),
+        };
+        operation.save(git_client)?;
+
+        Ok(operation)
+    }
+
+    /// Fails if another operation is in progress, so that commands can check it before modifying
+    /// anything.
+    pub fn ensure_none_in_progress(git_client: &dyn GitClient) -> Result<()> {
+        if let Some(operation) = Self::load(git_client)? {
+            return Err(eyre!("A gi {} is already in progress.", operation.name)).suggestion(
+                "Run `gi continue` to resume it or `gi abort` to roll it back before starting another one.",
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Loads the operation in progress, if any.
+    pub fn load(git_client: &dyn GitClient) -> Result<Option<Self>> {
+        let path = journal_path(git_client)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path).context("Failed to read operation journal")?;
+        let operation = serde_json::from_str::<Self>(&content)
+            .context("Failed to parse operation journal")
+            .suggestion(format!(
+                "Remove {} if no gi command is running.",
+                path.display()
+            ))?;
+
+        Ok(Some(operation))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Restacks the pending branches one by one, journaling progress after each of them.
+    ///
+    /// Once every branch is restacked, the original branch is checked out again and the journal
+    /// is removed.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the branches that were restacked, parents before children.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error suggesting `gi continue` or `gi abort` if a rebase stops on a conflict.
+    pub fn run(mut self, git_client: &dyn GitClient) -> Result<Vec<String>> {
+        while let Some(branch) = self.pending.first().cloned() {
+            let Some(metadata) = read_branch_metadata(git_client, &branch)? else {
+                self.pending.remove(0);
+                continue;
+            };
+
+            if git_client
+                .get_branch_revision(metadata.parent_branch_name())
+                .is_err()
+            {
+                output::print(format!(
+                    "Skipping {}: its parent {} no longer exists.",
+                    branch,
+                    metadata.parent_branch_name()
+                ));
+            } else if needs_restack(git_client, &metadata) {
+                match restack_branch(git_client, &branch, &metadata)? {
+                    RebaseStatus::Success => {
+                        output::print(format!(
+                            "Restacked {} onto {}.",
+                            branch,
+                            metadata.parent_branch_name()
+                        ));
+                        self.restacked.push(branch.clone());
+                    }
+                    RebaseStatus::Conflict => {
+                        self.save(git_client)?;
+
+                        return Err(eyre!("Restacking {} stopped on a conflict.", branch))
+                            .suggestion(format!(
+                                "Resolve the conflicts and run `gi continue`, or run `gi abort` to undo the whole gi {}.",
+                                self.name
+                            ));
+                    }
+                }
+            }
+
+            self.pending.remove(0);
+            self.save(git_client)?;
+        }
+
+        if let Some(original_branch) = &self.original_branch {
+            git_client.checkout(original_branch)?;
+        }
+
+        self.finish(git_client)?;
+
+        Ok(self.restacked)
+    }
+
+    /// Resumes the operation after the user resolved a conflict.
+    pub fn resume(self, git_client: &dyn GitClient) -> Result<Vec<String>> {
+        if git_client.is_rebase_in_progress()? {
+            if let RebaseStatus::Conflict = git_client.rebase_continue()? {
+                return Err(eyre!("There are still unresolved conflicts.")).suggestion(
+                    "Resolve the conflicts, stage them with `git add` and run `gi continue` again.",
+                );
+            }
+        }
+
+        self.run(git_client)
+    }
+
+    /// Rolls back every branch and its metadata to their state before the operation started.
+    pub fn rollback(self, git_client: &dyn GitClient) -> Result<()> {
+        if git_client.is_rebase_in_progress()? {
+            git_client.rebase_abort()?;
+        }
+
+        // Detach HEAD so that the refs can be moved without touching the working tree
+        let head = git_client.get_branch_revision("HEAD")?;
+        git_client.checkout(&head)?;
+
+        for snapshot in &self.snapshots {
+            let branch_ref = format!("refs/heads/{}", snapshot.branch);
+            match &snapshot.revision {
+                Some(revision) => git_client.update_ref(&branch_ref, revision)?,
+                None => {
+                    if git_client.get_branch_revision(&branch_ref).is_ok() {
+                        git_client.delete_ref(&branch_ref)?;
+                    }
+                }
+            }
+
+            let metadata_ref = format!("{BRANCH_METADATA_REF_PREFIX}{}", snapshot.branch);
+            match &snapshot.metadata {
+                Some(oid) => git_client.update_ref(&metadata_ref, oid)?,
+                None => {
+                    if get_branch_metadata_oid(git_client, &snapshot.branch).is_some() {
+                        git_client.delete_ref(&metadata_ref)?;
+                    }
+                }
+            }
+        }
+
+        if let Some(original_branch) = &self.original_branch {
+            git_client.checkout(original_branch)?;
+        }
+
+        self.finish(git_client)
+    }
+
+    fn save(&self, git_client: &dyn GitClient) -> Result<()> {
+        let content = serde_json::to_string(self)?;
+
+        std::fs::write(journal_path(git_client)?, content)
+            .context("Failed to save operation journal")
+            .suggestion("Check if you have write permissions to the .git directory.")?;
+
+        Ok(())
+    }
+
+    fn finish(&self, git_client: &dyn GitClient) -> Result<()> {
+        std::fs::remove_file(journal_path(git_client)?)
+            .context("Failed to remove operation journal")?;
+
+        Ok(())
+    }
+}
+
+fn journal_path(git_client: &dyn GitClient) -> Result<PathBuf> {
+    // Linked worktrees have their own git directory, so an operation in one doesn't block the others
+    let Some(git_dir) = git_client.get_git_dir() else {
+        return Err(eyre!("You are not inside a git repository.").suggestion(
+            "Run `gi` inside a git repository or run `git init` to create a new one.",
+        ));
+    };
+
+    Ok(Path::new(&git_dir).join(".gi_operation"))
+}
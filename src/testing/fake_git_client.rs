@@ -0,0 +1,725 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Mutex, MutexGuard},
+};
+
+use eyre::{OptionExt, Result};
+use tempfile::TempDir;
+
+use crate::{
+    commands::create::{create, CommitOptions},
+    git_client::{CommitStatus, GitClient, RebaseStatus, RepositoryInfo, WorkingArea},
+};
+
+const BRANCH_REF_PREFIX: &str = "refs/heads/";
+
+#[derive(Debug, Clone)]
+enum Object {
+    Blob(String),
+    Commit {
+        parent: Option<String>,
+        title: String,
+        body: String,
+        /// The files the commit changes.
+        files: Vec<String>,
+    },
+}
+
+/// A push recorded by `FakeGitClient::push_branch`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Push {
+    pub remote: String,
+    pub branch: String,
+    /// The commit the branch pointed to when it was pushed.
+    pub revision: String,
+    pub force: bool,
+}
+
+#[derive(Debug)]
+struct Repository {
+    objects: BTreeMap<String, Object>,
+    /// Every reference, including branches under `refs/heads/`.
+    refs: BTreeMap<String, String>,
+    /// The checked out branch, or the commit HEAD is detached at.
+    head: String,
+    working_area: WorkingArea,
+    commit_messages: VecDeque<(String, String)>,
+    /// The messages the editor was prefilled with, oldest first.
+    prefilled_messages: Vec<String>,
+    hunk_selections: VecDeque<Vec<String>>,
+    remotes: BTreeMap<String, RepositoryInfo>,
+    pushes: Vec<Push>,
+    next_oid: u64,
+}
+
+impl Repository {
+    fn write_object(&mut self, object: Object) -> String {
+        self.next_oid += 1;
+        let oid = format!("{:040x}", self.next_oid);
+        self.objects.insert(oid.clone(), object);
+
+        oid
+    }
+
+    fn resolve(&self, revision: &str) -> Option<String> {
+        if revision == "HEAD" {
+            if self.is_detached() {
+                return Some(self.head.clone());
+            }
+
+            return self.refs.get(&branch_ref(&self.head)).cloned();
+        }
+
+        self.refs
+            .get(&branch_ref(revision))
+            .or_else(|| self.refs.get(revision))
+            .cloned()
+            .or_else(|| {
+                self.objects
+                    .contains_key(revision)
+                    .then(|| revision.to_string())
+            })
+    }
+
+    fn commit(&self, revision: &str) -> Result<(Option<String>, String, String)> {
+        let oid = self
+            .resolve(revision)
+            .ok_or_else(|| eyre::eyre!("Unknown revision {revision}"))?;
+
+        match self.objects.get(&oid) {
+            Some(Object::Commit {
+                parent,
+                title,
+                body,
+                ..
+            }) => Ok((parent.clone(), title.clone(), body.clone())),
+            _ => Err(eyre::eyre!("{revision} is not a commit")),
+        }
+    }
+
+    /// Returns the commits reachable from a revision, starting with the revision itself.
+    fn history(&self, revision: &str) -> Vec<String> {
+        let mut history = Vec::new();
+        let mut next = self.resolve(revision);
+
+        while let Some(oid) = next {
+            next = match self.objects.get(&oid) {
+                Some(Object::Commit { parent, .. }) => parent.clone(),
+                _ => None,
+            };
+            history.push(oid);
+        }
+
+        history
+    }
+
+    fn commit_staged(&mut self, parent: Option<String>) -> CommitStatus {
+        if self.working_area.staged_files.is_empty() {
+            return CommitStatus::Aborted;
+        }
+        let Some((title, body)) = self.commit_messages.pop_front() else {
+            return CommitStatus::Aborted;
+        };
+
+        let mut files = std::mem::take(&mut self.working_area.staged_files);
+        files.retain(|file| !file.is_empty());
+        let oid = self.write_object(Object::Commit {
+            parent,
+            title,
+            body,
+            files,
+        });
+        self.move_head(oid);
+
+        CommitStatus::Success
+    }
+
+    fn is_detached(&self) -> bool {
+        self.objects.contains_key(&self.head)
+    }
+
+    /// Points HEAD at the specified commit, through the checked out branch unless HEAD is detached.
+    fn move_head(&mut self, oid: String) {
+        if self.is_detached() {
+            self.head = oid;
+        } else {
+            self.refs.insert(branch_ref(&self.head), oid);
+        }
+    }
+}
+
+/// Splits a commit message into its title, the first line, and its body.
+fn split_message(message: &str) -> (String, String) {
+    let (title, body) = message.split_once('\n').unwrap_or((message, ""));
+
+    (title.trim().to_string(), body.trim().to_string())
+}
+
+fn remote_ref(remote: &str, branch: &str) -> String {
+    format!("refs/remotes/{remote}/{branch}")
+}
+
+fn branch_ref(branch: &str) -> String {
+    format!("{BRANCH_REF_PREFIX}{branch}")
+}
+
+/// An in-memory `GitClient`, holding branches, references, blobs, commits and a working area.
+///
+/// Interactive commits take their messages from the ones queued with `queue_commit_message` and
+/// abort when none is left, like closing the editor without saving would. Interactive picks of
+/// hunks stage the files queued with `queue_hunk_selection`, and rebases never conflict.
+pub struct FakeGitClient {
+    repository: Mutex<Repository>,
+    /// The directory holding an empty `.git` directory, for the files gi keeps next to the
+    /// repository, e.g. the journal of multi-branch operations. It's removed with the client.
+    root: TempDir,
+}
+
+impl FakeGitClient {
+    /// Creates a repository with a single commit on the trunk, which is checked out.
+    pub fn new(trunk: &str) -> Self {
+        let mut repository = Repository {
+            objects: BTreeMap::new(),
+            refs: BTreeMap::new(),
+            head: trunk.to_string(),
+            working_area: WorkingArea {
+                staged_files: Vec::new(),
+                unstaged_files: Vec::new(),
+                untracked_files: Vec::new(),
+            },
+            commit_messages: VecDeque::new(),
+            prefilled_messages: Vec::new(),
+            hunk_selections: VecDeque::new(),
+            remotes: BTreeMap::new(),
+            pushes: Vec::new(),
+            next_oid: 0,
+        };
+
+        let root = repository.write_object(Object::Commit {
+            parent: None,
+            title: "Initial commit".to_string(),
+            body: String::new(),
+            files: Vec::new(),
+        });
+        repository.refs.insert(branch_ref(trunk), root);
+
+        let root = tempfile::tempdir().expect("Failed to create the repository root");
+        std::fs::create_dir(root.path().join(".git")).expect("Failed to create the .git directory");
+
+        Self {
+            repository: Mutex::new(repository),
+            root,
+        }
+    }
+
+    fn repository(&self) -> MutexGuard<'_, Repository> {
+        self.repository
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Adds a remote pointing to the specified repository.
+    pub fn add_remote(&self, name: &str, repository: RepositoryInfo) {
+        self.repository()
+            .remotes
+            .insert(name.to_string(), repository);
+    }
+
+    /// Marks files as modified in the working tree, without staging them.
+    pub fn modify_files(&self, files: &[&str]) {
+        let mut repository = self.repository();
+        for file in files {
+            repository
+                .working_area
+                .unstaged_files
+                .push(file.to_string());
+        }
+    }
+
+    /// Marks files as modified and staged.
+    pub fn stage_files(&self, files: &[&str]) {
+        let mut repository = self.repository();
+        for file in files {
+            repository.working_area.staged_files.push(file.to_string());
+        }
+    }
+
+    /// Queues the message the next interactive commit or amend is made with.
+    pub fn queue_commit_message(&self, title: &str, body: &str) {
+        self.repository()
+            .commit_messages
+            .push_back((title.to_string(), body.to_string()));
+    }
+
+    /// Returns the messages the editor of interactive commits was prefilled with, oldest first.
+    pub fn prefilled_messages(&self) -> Vec<String> {
+        self.repository().prefilled_messages.clone()
+    }
+
+    /// Queues the files the next interactive pick of hunks stages.
+    pub fn queue_hunk_selection(&self, files: &[&str]) {
+        self.repository()
+            .hunk_selections
+            .push_back(files.iter().map(|file| file.to_string()).collect());
+    }
+
+    /// Creates a branch tracked on top of the current one like `gi create` does, committing the
+    /// specified files with the message, and returns its name.
+    pub fn create_tracked_branch(&self, message: &str, files: &[&str]) -> Result<String> {
+        self.stage_files(files);
+        let options = CommitOptions {
+            all: false,
+            message: Some(message.to_string()),
+            interactive: false,
+        };
+        create(self, &options)?;
+
+        self.get_current_branch()
+            .ok_or_eyre("Failed to get the created branch")
+    }
+
+    /// Stages the specified files and commits them onto the current branch.
+    pub fn commit(&self, title: &str, files: &[&str]) -> Result<()> {
+        self.stage_files(files);
+        self.queue_commit_message(title, "");
+
+        match self.interactive_commit()? {
+            CommitStatus::Success => Ok(()),
+            CommitStatus::Aborted => Err(eyre::eyre!("Nothing to commit")),
+        }
+    }
+
+    /// Moves a branch of a remote, as someone pushing to it would, so that fetching it picks up the revision.
+    pub fn set_remote_branch(&self, remote: &str, branch: &str, revision: &str) {
+        self.repository()
+            .refs
+            .insert(remote_ref(remote, branch), revision.to_string());
+    }
+
+    /// Returns the names of every branch, ordered by name.
+    pub fn branches(&self) -> Vec<String> {
+        self.repository()
+            .refs
+            .keys()
+            .filter_map(|refname| refname.strip_prefix(BRANCH_REF_PREFIX))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Returns the titles of the commits reachable from a revision, newest first.
+    pub fn log(&self, revision: &str) -> Vec<String> {
+        let repository = self.repository();
+
+        repository
+            .history(revision)
+            .iter()
+            .filter_map(|oid| match repository.objects.get(oid) {
+                Some(Object::Commit { title, .. }) => Some(title.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns every push made so far, oldest first.
+    pub fn pushes(&self) -> Vec<Push> {
+        self.repository().pushes.clone()
+    }
+}
+
+impl GitClient for FakeGitClient {
+    fn interactive_commit(&self) -> Result<CommitStatus> {
+        let mut repository = self.repository();
+        let parent = repository.resolve("HEAD");
+
+        Ok(repository.commit_staged(parent))
+    }
+
+    fn interactive_commit_with_message(&self, message: &str) -> Result<CommitStatus> {
+        // The queued message stands for the one the user saves in the editor
+        self.repository()
+            .prefilled_messages
+            .push(message.to_string());
+
+        self.interactive_commit()
+    }
+
+    fn interactive_amend(&self) -> Result<CommitStatus> {
+        let mut repository = self.repository();
+        let (parent, title, body) = repository.commit("HEAD")?;
+
+        // Amending without changes rewords the commit, and keeps its message when none is queued
+        if repository.working_area.staged_files.is_empty() {
+            repository.working_area.staged_files.push(String::new());
+        }
+        if repository.commit_messages.is_empty() {
+            repository.commit_messages.push_back((title, body));
+        }
+        if let Some(Object::Commit { files, .. }) = repository
+            .resolve("HEAD")
+            .and_then(|oid| repository.objects.get(&oid).cloned())
+        {
+            repository.working_area.staged_files.extend(files);
+        }
+
+        Ok(repository.commit_staged(parent))
+    }
+
+    fn commit_with_message(&self, message: &str) -> Result<()> {
+        let (title, body) = split_message(message);
+        self.queue_commit_message(&title, &body);
+
+        match self.interactive_commit()? {
+            CommitStatus::Success => Ok(()),
+            CommitStatus::Aborted => Err(eyre::eyre!("Nothing to commit")),
+        }
+    }
+
+    fn amend_with_message(&self, message: Option<&str>) -> Result<()> {
+        if let Some(message) = message {
+            let (title, body) = split_message(message);
+            self.queue_commit_message(&title, &body);
+        }
+
+        match self.interactive_amend()? {
+            CommitStatus::Success => Ok(()),
+            CommitStatus::Aborted => Err(eyre::eyre!("Nothing to amend")),
+        }
+    }
+
+    fn checkout(&self, branch: &str) -> Result<()> {
+        let mut repository = self.repository();
+        // Like git, detaches HEAD when given a commit instead of a branch
+        if !repository.refs.contains_key(&branch_ref(branch))
+            && !repository.objects.contains_key(branch)
+        {
+            return Err(eyre::eyre!("Branch {branch} does not exist"));
+        }
+
+        repository.head = branch.to_string();
+
+        Ok(())
+    }
+
+    fn create_branch(&self, branch: &str) -> Result<String> {
+        let mut repository = self.repository();
+        let revision = repository
+            .resolve("HEAD")
+            .ok_or_eyre("Can't create a branch without commits")?;
+
+        // Like the CLI client, picks another name when the branch already exists
+        let mut name = branch.to_string();
+        let mut suffix = 0;
+        while repository.refs.contains_key(&branch_ref(&name)) {
+            suffix += 1;
+            name = format!("{branch}{suffix}");
+        }
+
+        repository.refs.insert(branch_ref(&name), revision);
+
+        Ok(name)
+    }
+
+    fn get_repository_info(&self, remote: &str) -> Result<RepositoryInfo> {
+        self.repository()
+            .remotes
+            .get(remote)
+            .cloned()
+            .ok_or_else(|| eyre::eyre!("Remote {remote} doesn't exist"))
+    }
+
+    fn list_remotes(&self) -> Result<Vec<String>> {
+        Ok(self.repository().remotes.keys().cloned().collect())
+    }
+
+    fn get_repository_root(&self) -> Option<String> {
+        Some(self.root.path().to_string_lossy().into_owned())
+    }
+
+    fn get_git_dir(&self) -> Option<String> {
+        Some(self.root.path().join(".git").to_string_lossy().into_owned())
+    }
+
+    fn get_git_common_dir(&self) -> Option<String> {
+        self.get_git_dir()
+    }
+
+    fn get_current_branch(&self) -> Option<String> {
+        let repository = self.repository();
+
+        (!repository.is_detached()).then(|| repository.head.clone())
+    }
+
+    fn get_commit_title(&self, revision: &str) -> Result<String> {
+        Ok(self.repository().commit(revision)?.1)
+    }
+
+    fn get_commit_body(&self, revision: &str) -> Result<String> {
+        Ok(self.repository().commit(revision)?.2)
+    }
+
+    fn get_branch_revision(&self, branch: &str) -> Result<String> {
+        self.repository()
+            .resolve(branch)
+            .ok_or_else(|| eyre::eyre!("Branch {} does not exist", branch))
+    }
+
+    fn delete_branch(&self, branch: &str) -> Result<()> {
+        let mut repository = self.repository();
+        if repository.head == branch {
+            return Err(eyre::eyre!("Can't delete the checked out branch {branch}"));
+        }
+
+        repository
+            .refs
+            .remove(&branch_ref(branch))
+            .ok_or_else(|| eyre::eyre!("Branch {branch} does not exist"))?;
+
+        Ok(())
+    }
+
+    fn get_working_area(&self) -> Result<WorkingArea> {
+        let working_area = &self.repository().working_area;
+
+        Ok(WorkingArea {
+            staged_files: working_area.staged_files.clone(),
+            unstaged_files: working_area.unstaged_files.clone(),
+            untracked_files: working_area.untracked_files.clone(),
+        })
+    }
+
+    fn create_blob(&self, content: &str) -> Result<String> {
+        Ok(self
+            .repository()
+            .write_object(Object::Blob(content.to_string())))
+    }
+
+    fn read_object(&self, oid: &str) -> Result<String> {
+        match self.repository().objects.get(oid) {
+            Some(Object::Blob(content)) => Ok(content.clone()),
+            _ => Err(eyre::eyre!("Failed to read object {oid}")),
+        }
+    }
+
+    fn update_ref(&self, refname: &str, oid: &str) -> Result<()> {
+        let mut repository = self.repository();
+        if !repository.objects.contains_key(oid) {
+            return Err(eyre::eyre!("Failed to update reference"));
+        }
+
+        repository.refs.insert(refname.to_string(), oid.to_string());
+
+        Ok(())
+    }
+
+    fn delete_ref(&self, refname: &str) -> Result<()> {
+        self.repository()
+            .refs
+            .remove(refname)
+            .ok_or_eyre("Failed to delete reference")?;
+
+        Ok(())
+    }
+
+    fn list_refs(&self, prefix: &str) -> Result<Vec<(String, String)>> {
+        Ok(self
+            .repository()
+            .refs
+            .iter()
+            .filter(|(refname, _)| refname.starts_with(prefix))
+            .map(|(refname, oid)| (refname.clone(), oid.clone()))
+            .collect())
+    }
+
+    fn fetch_branch(&self, remote: &str, branch: &str) -> Result<()> {
+        let mut repository = self.repository();
+        if !repository.remotes.contains_key(remote) {
+            return Err(eyre::eyre!("Failed to fetch branch"));
+        }
+
+        // Branches never set with `set_remote_branch` are as up to date as the local ones
+        let Some(revision) = repository.refs.get(&remote_ref(remote, branch)).cloned() else {
+            return Ok(());
+        };
+        if let Some(local) = repository.resolve(branch) {
+            if !repository.history(&revision).contains(&local) {
+                return Err(eyre::eyre!("Failed to fast-forward {branch}"));
+            }
+        }
+        repository.refs.insert(branch_ref(branch), revision);
+
+        Ok(())
+    }
+
+    fn push_branch(&self, remote: &str, branch: &str, force: bool) -> Result<()> {
+        let mut repository = self.repository();
+        if !repository.remotes.contains_key(remote) {
+            return Err(eyre::eyre!("Failed to push branch"));
+        }
+
+        let revision = repository
+            .resolve(branch)
+            .ok_or_eyre("Failed to push branch")?;
+        repository.pushes.push(Push {
+            remote: remote.to_string(),
+            branch: branch.to_string(),
+            revision,
+            force,
+        });
+
+        Ok(())
+    }
+
+    fn rebase_onto(&self, new_base: &str, upstream: &str, branch: &str) -> Result<RebaseStatus> {
+        let mut repository = self.repository();
+        let new_base = repository
+            .resolve(new_base)
+            .ok_or_else(|| eyre::eyre!("Unknown revision {new_base}"))?;
+        let upstream_history = repository.history(upstream);
+
+        // Replays the commits of the branch that aren't part of the upstream, oldest first
+        let mut commits = repository
+            .history(branch)
+            .into_iter()
+            .take_while(|oid| !upstream_history.contains(oid))
+            .collect::<Vec<_>>();
+        commits.reverse();
+
+        let mut tip = new_base;
+        for oid in commits {
+            let Some(Object::Commit {
+                title, body, files, ..
+            }) = repository.objects.get(&oid).cloned()
+            else {
+                continue;
+            };
+
+            tip = repository.write_object(Object::Commit {
+                parent: Some(tip),
+                title,
+                body,
+                files,
+            });
+        }
+
+        repository.refs.insert(branch_ref(branch), tip);
+        repository.head = branch.to_string();
+
+        Ok(RebaseStatus::Success)
+    }
+
+    fn rebase_continue(&self) -> Result<RebaseStatus> {
+        Ok(RebaseStatus::Success)
+    }
+
+    fn rebase_abort(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn is_rebase_in_progress(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn is_ancestor(&self, ancestor: &str, descendant: &str) -> Result<bool> {
+        let repository = self.repository();
+        let ancestor = repository
+            .resolve(ancestor)
+            .ok_or_else(|| eyre::eyre!("Unknown revision {ancestor}"))?;
+
+        Ok(repository.history(descendant).contains(&ancestor))
+    }
+
+    fn merge_base(&self, first: &str, second: &str) -> Result<String> {
+        let repository = self.repository();
+        let first_history = repository.history(first);
+
+        // Commits have a single parent, so the first shared commit is the best common ancestor
+        repository
+            .history(second)
+            .into_iter()
+            .find(|oid| first_history.contains(oid))
+            .ok_or_else(|| eyre::eyre!("{first} and {second} have no common history"))
+    }
+
+    fn list_commits(&self, from: &str, to: &str) -> Result<Vec<String>> {
+        let repository = self.repository();
+        let excluded = repository.history(from);
+
+        let mut commits = repository
+            .history(to)
+            .into_iter()
+            .take_while(|oid| !excluded.contains(oid))
+            .collect::<Vec<_>>();
+        commits.reverse();
+
+        Ok(commits)
+    }
+
+    fn reset(&self, revision: &str) -> Result<()> {
+        let mut repository = self.repository();
+        let target = repository
+            .resolve(revision)
+            .ok_or_else(|| eyre::eyre!("Unknown revision {revision}"))?;
+        let target_history = repository.history(&target);
+
+        // The files of the commits left behind become unstaged changes, as do the staged ones
+        let mut changes = std::mem::take(&mut repository.working_area.staged_files);
+        for oid in repository.history("HEAD") {
+            if target_history.contains(&oid) {
+                break;
+            }
+            if let Some(Object::Commit { files, .. }) = repository.objects.get(&oid) {
+                changes.extend(files.iter().cloned());
+            }
+        }
+        // Moving forward instead commits the changes of the commits caught up with
+        let head_history = repository.history("HEAD");
+        let mut committed = Vec::new();
+        for oid in &target_history {
+            if head_history.contains(oid) {
+                break;
+            }
+            if let Some(Object::Commit { files, .. }) = repository.objects.get(oid) {
+                committed.extend(files.iter().cloned());
+            }
+        }
+
+        let working_area = &mut repository.working_area;
+        for file in changes {
+            if !working_area.unstaged_files.contains(&file) && !committed.contains(&file) {
+                working_area.unstaged_files.push(file);
+            }
+        }
+        working_area
+            .unstaged_files
+            .retain(|file| !committed.contains(file));
+
+        repository.move_head(target);
+
+        Ok(())
+    }
+
+    fn interactive_add(&self) -> Result<()> {
+        let mut repository = self.repository();
+        let selection = repository.hunk_selections.pop_front().unwrap_or_default();
+
+        let working_area = &mut repository.working_area;
+        for file in selection {
+            working_area.unstaged_files.retain(|f| *f != file);
+            working_area.untracked_files.retain(|f| *f != file);
+            working_area.staged_files.push(file);
+        }
+
+        Ok(())
+    }
+
+    fn add_all(&self) -> Result<()> {
+        let mut repository = self.repository();
+        let working_area = &mut repository.working_area;
+
+        let mut changes = std::mem::take(&mut working_area.unstaged_files);
+        changes.append(&mut working_area.untracked_files);
+        working_area.staged_files.append(&mut changes);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,22 @@
+//! In-memory stand-ins for the git repository and the provider, so commands can be tested
+//! without spawning `git` or talking to a real provider.
+
+pub mod fake_git_client;
+pub mod recording_provider;
+
+pub use fake_git_client::{FakeGitClient, Push};
+pub use recording_provider::{ProviderCall, RecordingProvider};
+
+use crate::{git_client::RepositoryInfo, git_provider::SupportedProviders};
+
+/// Describes a repository hosted on github.com.
+pub fn github_repository(owner: &str, repo: &str) -> RepositoryInfo {
+    RepositoryInfo {
+        provider: SupportedProviders::GitHub,
+        host: "github.com".to_string(),
+        owner: owner.to_string(),
+        project: None,
+        repo: repo.to_string(),
+        web_url: "https://github.com".to_string(),
+    }
+}
@@ -0,0 +1,224 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use eyre::{OptionExt, Result};
+
+use crate::{
+    git_client::RepositoryInfo,
+    git_provider::{GitProvider, PullRequest, PullRequestState},
+};
+
+/// A call made to a `RecordingProvider`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProviderCall {
+    Create {
+        /// The owner of the fork the pull request was opened from, if any.
+        fork_owner: Option<String>,
+        branch: String,
+        base: String,
+        title: String,
+        body: String,
+    },
+    Find {
+        fork_owner: Option<String>,
+        branch: String,
+    },
+    Get {
+        number: u64,
+    },
+    Update {
+        number: u64,
+        base: String,
+        title: String,
+        body: String,
+    },
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Every pull request opened so far, along with the branch it was opened from.
+    pull_requests: Vec<(String, PullRequest)>,
+    calls: Vec<ProviderCall>,
+}
+
+/// An in-memory `GitProvider` that keeps the pull requests opened through it and records every call.
+///
+/// Clones share their state, so a test can keep a clone around to inspect what a command did with
+/// the provider it was given.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingProvider {
+    state: Arc<Mutex<State>>,
+}
+
+impl RecordingProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns a provider factory handing out clones of this provider, whatever the repository.
+    pub fn factory(&self) -> impl Fn(&RepositoryInfo) -> Result<Box<dyn GitProvider>> {
+        let provider = self.clone();
+
+        move |_| Ok(Box::new(provider.clone()))
+    }
+
+    /// Returns every call made so far, oldest first.
+    pub fn calls(&self) -> Vec<ProviderCall> {
+        self.state().calls.clone()
+    }
+
+    /// Returns the pull requests opened so far, ordered by number.
+    pub fn pull_requests(&self) -> Vec<PullRequest> {
+        self.state()
+            .pull_requests
+            .iter()
+            .map(|(_, pull_request)| pull_request.clone())
+            .collect()
+    }
+
+    /// Merges or closes a pull request, as a reviewer would.
+    pub fn set_pull_request_state(&self, number: u64, pull_request_state: PullRequestState) {
+        let mut state = self.state();
+        if let Some((_, pull_request)) = state
+            .pull_requests
+            .iter_mut()
+            .find(|(_, pull_request)| pull_request.number == number)
+        {
+            pull_request.state = pull_request_state;
+        }
+    }
+
+    /// Returns the pull request opened from the specified branch, if any.
+    pub fn pull_request_for(&self, branch: &str) -> Option<PullRequest> {
+        self.state()
+            .pull_requests
+            .iter()
+            .find(|(head, _)| head == branch)
+            .map(|(_, pull_request)| pull_request.clone())
+    }
+}
+
+#[async_trait]
+impl GitProvider for RecordingProvider {
+    fn token_key(&self) -> String {
+        "example.com".to_string()
+    }
+
+    fn token_prompt(&self) -> String {
+        String::new()
+    }
+
+    fn get_token(&self) -> Result<String> {
+        Ok("token".to_string())
+    }
+
+    fn supports_forks(&self) -> bool {
+        true
+    }
+
+    async fn create_pull_request(
+        &self,
+        repository: &RepositoryInfo,
+        fork: Option<&RepositoryInfo>,
+        branch: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let mut state = self.state();
+        state.calls.push(ProviderCall::Create {
+            fork_owner: fork.map(|fork| fork.owner.clone()),
+            branch: branch.to_string(),
+            base: base.to_string(),
+            title: title.to_string(),
+            body: body.to_string(),
+        });
+
+        let number = state.pull_requests.len() as u64 + 1;
+        let pull_request = PullRequest {
+            number,
+            url: format!(
+                "https://{}/{}/{}/pull/{number}",
+                repository.host, repository.owner, repository.repo
+            ),
+            base: base.to_string(),
+            title: title.to_string(),
+            body: body.to_string(),
+            state: PullRequestState::Open,
+        };
+        state
+            .pull_requests
+            .push((branch.to_string(), pull_request.clone()));
+
+        Ok(pull_request)
+    }
+
+    async fn find_pull_request(
+        &self,
+        _repository: &RepositoryInfo,
+        fork: Option<&RepositoryInfo>,
+        branch: &str,
+    ) -> Result<Option<PullRequest>> {
+        let mut state = self.state();
+        state.calls.push(ProviderCall::Find {
+            fork_owner: fork.map(|fork| fork.owner.clone()),
+            branch: branch.to_string(),
+        });
+
+        Ok(state
+            .pull_requests
+            .iter()
+            .find(|(head, _)| head == branch)
+            .map(|(_, pull_request)| pull_request.clone()))
+    }
+
+    async fn get_pull_request(
+        &self,
+        _repository: &RepositoryInfo,
+        number: u64,
+    ) -> Result<PullRequest> {
+        let mut state = self.state();
+        state.calls.push(ProviderCall::Get { number });
+
+        state
+            .pull_requests
+            .iter()
+            .find(|(_, pull_request)| pull_request.number == number)
+            .map(|(_, pull_request)| pull_request.clone())
+            .ok_or_eyre("Failed to get pull request")
+    }
+
+    async fn update_pull_request(
+        &self,
+        _repository: &RepositoryInfo,
+        number: u64,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let mut state = self.state();
+        state.calls.push(ProviderCall::Update {
+            number,
+            base: base.to_string(),
+            title: title.to_string(),
+            body: body.to_string(),
+        });
+
+        let (_, pull_request) = state
+            .pull_requests
+            .iter_mut()
+            .find(|(_, pull_request)| pull_request.number == number)
+            .ok_or_eyre("Failed to update pull request")?;
+        pull_request.base = base.to_string();
+        pull_request.title = title.to_string();
+        pull_request.body = body.to_string();
+
+        Ok(pull_request.clone())
+    }
+}
@@ -0,0 +1,100 @@
+use color_eyre::Section;
+use eyre::{Context, Ok, OptionExt, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::create_dir_all,
+    path::PathBuf,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{git_client::GitBackend, git_provider::SupportedProviders, IssueError};
+
+/// How gi talks to a git host that it can't recognise by its name.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HostSettings {
+    pub provider: SupportedProviders,
+    /// The base URL of the provider API, e.g. `https://git.corp.example/api/v3`.
+    #[serde(rename = "apiUrl", default, skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+}
+
+/// Settings shared by every repository of the user, stored in `~/.config/gi/config`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct UserSettings {
+    #[serde(default)]
+    hosts: BTreeMap<String, HostSettings>,
+    /// How gi reads the repository, either `native` or `cli`.
+    #[serde(rename = "gitBackend", default)]
+    git_backend: GitBackend,
+}
+
+// Loading is retried on every call until it succeeds, so that errors reach the user with their suggestion
+static USER_SETTINGS: OnceCell<Mutex<UserSettings>> = OnceCell::new();
+
+pub fn get_user_settings() -> Result<MutexGuard<'static, UserSettings>> {
+    let guard = USER_SETTINGS
+        .get_or_try_init(|| UserSettings::load().map(Mutex::new))?
+        .lock()
+        .to_issue_error("Failed to get user settings lock")?;
+
+    Ok(guard)
+}
+
+/// Returns the directory gi stores its user-wide configuration in, `~/.config/gi`.
+pub fn get_config_dir() -> Result<PathBuf> {
+    let home_dir = dirs::home_dir().ok_or_eyre("Failed to get home directory")?;
+
+    Ok(home_dir.join(".config").join("gi"))
+}
+
+impl UserSettings {
+    fn load() -> Result<Self> {
+        let config_path = get_config_dir()?.join("config");
+        if !config_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&config_path)
+            .context("Failed to read user settings")
+            .suggestion("Check if you have read permissions to ~/.config/gi/config")?;
+
+        let settings = serde_json::from_str::<UserSettings>(&content)
+            .context("Failed to parse user settings")
+            .suggestion("Fix or remove ~/.config/gi/config")?;
+
+        Ok(settings)
+    }
+
+    /// Returns how to talk to the specified host, if it was configured.
+    pub fn get_host(&self, host: &str) -> Option<&HostSettings> {
+        self.hosts.get(host)
+    }
+
+    pub fn git_backend(&self) -> GitBackend {
+        self.git_backend
+    }
+
+    pub fn set_host(&mut self, host: &str, settings: HostSettings) -> Result<()> {
+        self.hosts.insert(host.to_string(), settings);
+        self.save()?;
+
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        let config_dir = get_config_dir()?;
+        create_dir_all(&config_dir)
+            .context("Failed to create gi config directory")
+            .suggestion("Check if you have write permissions to the .config directory")?;
+
+        let json_settings = serde_json::to_string_pretty(self)?;
+
+        std::fs::write(config_dir.join("config"), json_settings)
+            .context("Failed to save user settings")
+            .suggestion("Check if you have write permissions to the .config/gi directory.")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+mod common;
+
+use common::Sandbox;
+use httpmock::{Method, MockServer};
+use predicates::str::contains;
+use serde_json::{json, Value};
+
+const HOST: &str = "dev.azure.com";
+const REPOSITORY: &str = "org/project/_git/repo";
+const PULLS_PATH: &str = "/org/project/_apis/git/repositories/repo/pullrequests";
+// Personal access tokens are sent as the password of a user with an empty name
+const AUTHORIZATION: &str = "Basic OnRlc3QtdG9rZW4=";
+
+/// A pull request as the Azure DevOps API returns it.
+fn pull_request(id: u64, base: &str, status: &str, description: &str) -> Value {
+    json!({
+        "pullRequestId": id,
+        "repository": { "webUrl": format!("https://{HOST}/{REPOSITORY}") },
+        "targetRefName": format!("refs/heads/{base}"),
+        "title": "Add the lexer",
+        "description": description,
+        "status": status,
+    })
+}
+
+#[test]
+fn submit_opens_a_pull_request() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, REPOSITORY, "AzureDevOps", &server);
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+
+    let find = server.mock(|when, then| {
+        when.method(Method::GET)
+            .path(PULLS_PATH)
+            .query_param("api-version", "7.0")
+            .query_param(
+                "searchCriteria.sourceRefName",
+                format!("refs/heads/{branch}"),
+            )
+            .query_param("searchCriteria.status", "active")
+            .header("authorization", AUTHORIZATION);
+        then.status(200).json_body(json!({ "value": [] }));
+    });
+    let create = server.mock(|when, then| {
+        when.method(Method::POST).path(PULLS_PATH).json_body(json!({
+            "sourceRefName": format!("refs/heads/{branch}"),
+            "targetRefName": "refs/heads/main",
+            "title": "Add the lexer",
+            "description": "",
+        }));
+        then.status(201)
+            .json_body(pull_request(1, "main", "active", ""));
+    });
+    let update = server.mock(|when, then| {
+        when.method(Method::PATCH)
+            .path(format!("{PULLS_PATH}/1"))
+            .body_contains("gi-stack-start");
+        then.status(200)
+            .json_body(pull_request(1, "main", "active", "stack"));
+    });
+
+    let output = sandbox.gi(&["submit", "--json"]).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        serde_json::from_slice::<Value>(&output.stdout).unwrap()["pullRequests"][0]["url"],
+        "https://dev.azure.com/org/project/_git/repo/pullrequest/1"
+    );
+
+    find.assert();
+    create.assert();
+    update.assert();
+    assert_eq!(sandbox.metadata(&branch).unwrap()["prNumber"], 1);
+}
+
+#[test]
+fn submit_updates_the_existing_pull_request() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, REPOSITORY, "AzureDevOps", &server);
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+
+    server.mock(|when, then| {
+        when.method(Method::GET).path(PULLS_PATH);
+        then.status(200)
+            .json_body(json!({ "value": [pull_request(4, "develop", "active", "Tokenizes.")] }));
+    });
+    let retarget = server.mock(|when, then| {
+        when.method(Method::PATCH)
+            .path(format!("{PULLS_PATH}/4"))
+            .header("authorization", AUTHORIZATION)
+            .json_body(json!({
+                "targetRefName": "refs/heads/main",
+                "title": "Add the lexer",
+                "description": "Tokenizes.",
+            }));
+        then.status(200)
+            .json_body(pull_request(4, "main", "active", "Tokenizes."));
+    });
+    server.mock(|when, then| {
+        when.method(Method::PATCH)
+            .path(format!("{PULLS_PATH}/4"))
+            .body_contains("gi-stack-start");
+        then.status(200)
+            .json_body(pull_request(4, "main", "active", "stack"));
+    });
+
+    sandbox.gi(&["submit"]).assert().success();
+
+    retarget.assert();
+    assert_eq!(sandbox.metadata(&branch).unwrap()["prNumber"], 4);
+}
+
+#[test]
+fn sync_deletes_branches_of_completed_pull_requests() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, REPOSITORY, "AzureDevOps", &server);
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let mut metadata = sandbox.metadata(&branch).unwrap();
+    metadata["prNumber"] = json!(3);
+    sandbox.set_metadata(&branch, &metadata);
+
+    let get = server.mock(|when, then| {
+        when.method(Method::GET)
+            .path(format!("{PULLS_PATH}/3"))
+            .query_param("api-version", "7.0");
+        then.status(200)
+            .json_body(pull_request(3, "main", "completed", ""));
+    });
+
+    sandbox.gi(&["sync", "--force"]).assert().success();
+
+    get.assert();
+    assert_eq!(sandbox.branches(), vec!["main".to_string()]);
+    assert!(sandbox.metadata(&branch).is_none());
+}
+
+#[test]
+fn submit_reports_api_errors() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, REPOSITORY, "AzureDevOps", &server);
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+
+    server.mock(|when, then| {
+        when.method(Method::GET).path(PULLS_PATH);
+        then.status(203).body("<html>Sign in</html>");
+    });
+
+    // Azure DevOps answers requests with an expired token with a sign-in page instead of an error
+    sandbox
+        .gi(&["submit"])
+        .assert()
+        .failure()
+        .stderr(contains("Failed to parse the provider response"));
+
+    assert!(sandbox.metadata(&branch).unwrap().get("prNumber").is_none());
+}
+
+#[test]
+fn submit_reports_rejected_pull_requests() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, REPOSITORY, "AzureDevOps", &server);
+    sandbox.create_branch("lexer.rs", "Add the lexer");
+
+    server.mock(|when, then| {
+        when.method(Method::GET).path(PULLS_PATH);
+        then.status(200).json_body(json!({ "value": [] }));
+    });
+    server.mock(|when, then| {
+        when.method(Method::POST).path(PULLS_PATH);
+        then.status(409)
+            .json_body(json!({ "message": "An active pull request already exists." }));
+    });
+
+    sandbox
+        .gi(&["submit"])
+        .assert()
+        .failure()
+        .stderr(contains("Failed to create pull request"));
+}
@@ -0,0 +1,291 @@
+//! Scratch repositories for driving the `gi` binary end to end.
+//!
+//! Every sandbox has its own home directory, a bare repository standing in for the remote and a
+//! working clone of it with a single commit on `main`. The remote is registered under a
+//! provider URL, github.com by default, that git rewrites to the bare repository, so that gi
+//! recognises the provider while pushes stay local.
+
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use assert_cmd::{assert::Assert, cargo::CommandCargoExt};
+use httpmock::MockServer;
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+pub const OWNER: &str = "owner";
+pub const REPO: &str = "repo";
+
+pub struct Sandbox {
+    // Removes every directory of the sandbox once the test is over
+    _root: TempDir,
+    /// The host of the provider the remote is registered under.
+    host: String,
+    home: PathBuf,
+    remote: PathBuf,
+    work: PathBuf,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        Self::with_remote("github.com", &format!("{OWNER}/{REPO}"))
+    }
+
+    /// Creates a sandbox whose remote is registered under `https://{host}/{path}.git`.
+    pub fn with_remote(host: &str, path: &str) -> Self {
+        let root = tempfile::tempdir().expect("Failed to create sandbox");
+        let home = root.path().join("home");
+        let remote = root.path().join("remote.git");
+        let work = root.path().join("work");
+        fs::create_dir_all(&home).unwrap();
+
+        let sandbox = Self {
+            _root: root,
+            host: host.to_string(),
+            home,
+            remote,
+            work,
+        };
+
+        run_git(
+            sandbox.home.as_path(),
+            &sandbox,
+            &[
+                "init",
+                "--bare",
+                "-b",
+                "main",
+                sandbox.remote.to_str().unwrap(),
+            ],
+        );
+        run_git(
+            sandbox.home.as_path(),
+            &sandbox,
+            &["init", "-b", "main", sandbox.work.to_str().unwrap()],
+        );
+
+        let remote_url = format!("https://{host}/{path}.git");
+        sandbox.git(&["remote", "add", "origin", &remote_url]);
+        sandbox.git(&[
+            "config",
+            &format!("url.{}.insteadOf", sandbox.remote.display()),
+            &remote_url,
+        ]);
+
+        sandbox.write_file("README.md", "# repo\n");
+        sandbox.git(&["add", "-A"]);
+        sandbox.git(&["commit", "-m", "Initial commit"]);
+        sandbox.git(&["push", "origin", "main"]);
+
+        // Answers the trunk prompt up front
+        fs::write(
+            sandbox.work.join(".git/.gi_project_config"),
+            json!({ "trunk": "main" }).to_string(),
+        )
+        .unwrap();
+
+        sandbox
+    }
+
+    /// Creates a sandbox whose remote is registered under `https://{host}/{path}.git`, with the
+    /// host mapped to the specified provider and its API served by `server`.
+    pub fn for_provider(host: &str, path: &str, provider: &str, server: &MockServer) -> Self {
+        let sandbox = Self::with_remote(host, path);
+        sandbox.use_provider_api(provider, &server.base_url(), "test-token");
+
+        sandbox
+    }
+
+    pub fn work_dir(&self) -> &Path {
+        &self.work
+    }
+
+    /// Routes github.com API calls to the specified server, and stores a token for it.
+    pub fn use_github_api(&self, api_url: &str) {
+        self.use_provider_api("GitHub", api_url, "test-token");
+    }
+
+    /// Maps the host of the remote to the specified provider and API server, and stores the
+    /// credentials for it.
+    pub fn use_provider_api(&self, provider: &str, api_url: &str, token: &str) {
+        let config_dir = self.home.join(".config/gi");
+        fs::create_dir_all(&config_dir).unwrap();
+
+        let mut hosts = serde_json::Map::new();
+        hosts.insert(
+            self.host.clone(),
+            json!({ "provider": provider, "apiUrl": api_url }),
+        );
+        fs::write(
+            config_dir.join("config"),
+            json!({ "hosts": hosts }).to_string(),
+        )
+        .unwrap();
+
+        let mut tokens = serde_json::Map::new();
+        tokens.insert(self.host.clone(), json!(token));
+        fs::write(config_dir.join("token"), Value::Object(tokens).to_string()).unwrap();
+    }
+
+    /// Runs git in the working repository and returns its trimmed stdout.
+    pub fn git(&self, args: &[&str]) -> String {
+        run_git(&self.work, self, args)
+    }
+
+    /// Runs git in the bare remote repository and returns its trimmed stdout.
+    pub fn remote_git(&self, args: &[&str]) -> String {
+        run_git(&self.remote, self, args)
+    }
+
+    /// Returns a command running the `gi` binary in the working repository, without a terminal.
+    pub fn gi(&self, args: &[&str]) -> assert_cmd::Command {
+        let mut command = Command::cargo_bin("gi").unwrap();
+        self.configure(&mut command);
+        command.current_dir(&self.work).args(args);
+
+        let mut command = assert_cmd::Command::from_std(command);
+        command.write_stdin("");
+
+        command
+    }
+
+    /// Runs `gi create`, committing the staged changes with the specified message.
+    pub fn create(&self, message: &str) -> Assert {
+        self.gi(&["create", "-m", message]).assert()
+    }
+
+    /// Stages a file and creates a stacked branch committing it on top of the current branch, and
+    /// returns the name of the branch.
+    pub fn create_branch(&self, file: &str, message: &str) -> String {
+        self.stage_file(file, message);
+        self.create(message).success();
+
+        self.current_branch()
+    }
+
+    /// Writes a file in the working repository and stages it.
+    pub fn stage_file(&self, name: &str, content: &str) {
+        self.write_file(name, content);
+        self.git(&["add", name]);
+    }
+
+    pub fn write_file(&self, name: &str, content: &str) {
+        let path = self.work.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    pub fn current_branch(&self) -> String {
+        self.git(&["branch", "--show-current"])
+    }
+
+    pub fn branches(&self) -> Vec<String> {
+        self.git(&["for-each-ref", "--format=%(refname:short)", "refs/heads/"])
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn revision(&self, revision: &str) -> String {
+        self.git(&["rev-parse", revision])
+    }
+
+    /// Returns the metadata gi stored for a branch, or `None` if the branch isn't tracked.
+    pub fn metadata(&self, branch: &str) -> Option<Value> {
+        let refname = format!("refs/branch-metadata/{branch}");
+        let output = self.git_output(&["cat-file", "-p", &refname]);
+        if !output.status.success() {
+            return None;
+        }
+
+        Some(serde_json::from_slice(&output.stdout).expect("Invalid branch metadata"))
+    }
+
+    /// Overwrites the metadata gi stored for a branch.
+    pub fn set_metadata(&self, branch: &str, metadata: &Value) {
+        let mut command = Command::new("git");
+        self.configure(&mut command);
+        let mut child = command
+            .current_dir(&self.work)
+            .args(["hash-object", "-w", "--stdin"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        std::io::Write::write_all(
+            &mut child.stdin.take().unwrap(),
+            metadata.to_string().as_bytes(),
+        )
+        .unwrap();
+        let output = child.wait_with_output().unwrap();
+        let oid = String::from_utf8(output.stdout).unwrap();
+
+        self.git(&[
+            "update-ref",
+            &format!("refs/branch-metadata/{branch}"),
+            oid.trim(),
+        ]);
+    }
+
+    pub fn project_config(&self) -> Value {
+        let content = fs::read_to_string(self.work.join(".git/.gi_project_config")).unwrap();
+
+        serde_json::from_str(&content).unwrap()
+    }
+
+    /// Overwrites `~/.config/gi/config` with the specified content, valid or not.
+    pub fn write_user_config(&self, content: &str) {
+        let config_dir = self.home.join(".config/gi");
+        fs::create_dir_all(&config_dir).unwrap();
+
+        fs::write(config_dir.join("config"), content).unwrap();
+    }
+
+    pub fn user_config(&self) -> Value {
+        let content = fs::read_to_string(self.home.join(".config/gi/config")).unwrap();
+
+        serde_json::from_str(&content).unwrap()
+    }
+
+    fn git_output(&self, args: &[&str]) -> std::process::Output {
+        let mut command = Command::new("git");
+        self.configure(&mut command);
+
+        command.current_dir(&self.work).args(args).output().unwrap()
+    }
+
+    /// Isolates a command from the configuration of the user running the tests.
+    fn configure(&self, command: &mut Command) {
+        command
+            .env("HOME", &self.home)
+            .env("XDG_CONFIG_HOME", self.home.join(".config"))
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_AUTHOR_NAME", "Test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "Test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .env("GIT_EDITOR", "true")
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE");
+    }
+}
+
+fn run_git(dir: &Path, sandbox: &Sandbox, args: &[&str]) -> String {
+    let mut command = Command::new("git");
+    sandbox.configure(&mut command);
+
+    let output = command.current_dir(dir).args(args).output().unwrap();
+    assert!(
+        output.status.success(),
+        "git {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
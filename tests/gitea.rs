@@ -0,0 +1,198 @@
+mod common;
+
+use common::{Sandbox, OWNER, REPO};
+use httpmock::{Method, Mock, MockServer};
+use predicates::str::contains;
+use serde_json::{json, Value};
+
+const HOST: &str = "gitea.example.com";
+const PULLS_PATH: &str = "/repos/owner/repo/pulls";
+/// The number of pull requests gi asks for per page.
+const PAGE_SIZE: u64 = 50;
+
+/// A pull request as the Gitea API returns it.
+fn pull_request(number: u64, branch: &str, base: &str, body: &str) -> Value {
+    json!({
+        "number": number,
+        "html_url": format!("https://{HOST}/{OWNER}/{REPO}/pulls/{number}"),
+        "head": { "ref": branch, "repo": { "owner": { "login": OWNER } } },
+        "base": { "ref": base },
+        "title": "Add the lexer",
+        "body": body,
+        "state": "open",
+        "merged": false,
+    })
+}
+
+/// Mocks a page of the open pull requests, returning the specified ones.
+fn mock_page(server: &MockServer, page: u32, found: Vec<Value>) -> Mock<'_> {
+    server.mock(|when, then| {
+        when.method(Method::GET)
+            .path(PULLS_PATH)
+            .query_param("state", "open")
+            .query_param("page", page.to_string())
+            .query_param("limit", PAGE_SIZE.to_string());
+        then.status(200).json_body(Value::Array(found));
+    })
+}
+
+#[test]
+fn submit_opens_a_pull_request() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, &format!("{OWNER}/{REPO}"), "Gitea", &server);
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+
+    // Gitea can't filter by head branch, so gi goes through the pages until a short one, skipping
+    // the pull requests of forks with a branch of the same name
+    let mut from_fork = pull_request(4, &branch, "main", "");
+    from_fork["head"]["repo"]["owner"]["login"] = json!("someone");
+    let first_page = mock_page(
+        &server,
+        1,
+        vec![pull_request(5, "other", "main", ""), from_fork],
+    );
+    let next_page = mock_page(&server, 2, vec![]);
+    let create = server.mock(|when, then| {
+        when.method(Method::POST)
+            .path(PULLS_PATH)
+            .header("authorization", "token test-token")
+            .json_body(json!({
+                "head": branch,
+                "base": "main",
+                "title": "Add the lexer",
+                "body": "",
+            }));
+        then.status(201)
+            .json_body(pull_request(6, &branch, "main", ""));
+    });
+    let update = server.mock(|when, then| {
+        when.method(Method::PATCH)
+            .path(format!("{PULLS_PATH}/6"))
+            .body_contains("gi-stack-start");
+        then.status(200)
+            .json_body(pull_request(6, &branch, "main", "stack"));
+    });
+
+    sandbox.gi(&["submit"]).assert().success();
+
+    first_page.assert();
+    next_page.assert_hits(0);
+    create.assert();
+    update.assert();
+    assert_eq!(sandbox.metadata(&branch).unwrap()["prNumber"], 6);
+}
+
+#[test]
+fn submit_finds_the_pull_request_on_a_later_page() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, &format!("{OWNER}/{REPO}"), "Gitea", &server);
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+
+    let full_page = (1..=PAGE_SIZE)
+        .map(|number| pull_request(100 + number, "other", "main", ""))
+        .collect();
+    mock_page(&server, 1, full_page);
+    mock_page(
+        &server,
+        2,
+        vec![pull_request(9, &branch, "develop", "Tokenizes.")],
+    );
+    let retarget = server.mock(|when, then| {
+        when.method(Method::PATCH)
+            .path(format!("{PULLS_PATH}/9"))
+            .json_body(json!({
+                "base": "main",
+                "title": "Add the lexer",
+                "body": "Tokenizes.",
+            }));
+        then.status(200)
+            .json_body(pull_request(9, &branch, "main", "Tokenizes."));
+    });
+    server.mock(|when, then| {
+        when.method(Method::PATCH)
+            .path(format!("{PULLS_PATH}/9"))
+            .body_contains("gi-stack-start");
+        then.status(200)
+            .json_body(pull_request(9, &branch, "main", "stack"));
+    });
+
+    sandbox.gi(&["submit"]).assert().success();
+
+    retarget.assert();
+    assert_eq!(sandbox.metadata(&branch).unwrap()["prNumber"], 9);
+}
+
+#[test]
+fn submit_gives_up_on_servers_ignoring_the_page() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, &format!("{OWNER}/{REPO}"), "Gitea", &server);
+    sandbox.create_branch("lexer.rs", "Add the lexer");
+
+    let full_page = (1..=PAGE_SIZE)
+        .map(|number| pull_request(number, "other", "main", ""))
+        .collect::<Vec<_>>();
+    server.mock(|when, then| {
+        when.method(Method::GET).path(PULLS_PATH);
+        then.status(200).json_body(Value::Array(full_page));
+    });
+    let create = server.mock(|when, then| {
+        when.method(Method::POST).path(PULLS_PATH);
+        then.status(201);
+    });
+
+    sandbox
+        .gi(&["submit"])
+        .assert()
+        .failure()
+        .stderr(contains("more than 100 pages"));
+
+    create.assert_hits(0);
+}
+
+#[test]
+fn sync_deletes_branches_of_merged_pull_requests() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, &format!("{OWNER}/{REPO}"), "Gitea", &server);
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let mut metadata = sandbox.metadata(&branch).unwrap();
+    metadata["prNumber"] = json!(3);
+    sandbox.set_metadata(&branch, &metadata);
+
+    // Merged pull requests are closed ones with the merged flag set
+    let mut merged = pull_request(3, &branch, "main", "");
+    merged["state"] = json!("closed");
+    merged["merged"] = json!(true);
+    let get = server.mock(|when, then| {
+        when.method(Method::GET)
+            .path(format!("{PULLS_PATH}/3"))
+            .header("authorization", "token test-token");
+        then.status(200).json_body(merged);
+    });
+
+    sandbox.gi(&["sync", "--force"]).assert().success();
+
+    get.assert();
+    assert_eq!(sandbox.branches(), vec!["main".to_string()]);
+    assert!(sandbox.metadata(&branch).is_none());
+}
+
+#[test]
+fn submit_reports_api_errors() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, &format!("{OWNER}/{REPO}"), "Gitea", &server);
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+
+    server.mock(|when, then| {
+        when.method(Method::GET).path(PULLS_PATH);
+        then.status(403)
+            .json_body(json!({ "message": "token does not have the required scope" }));
+    });
+
+    sandbox
+        .gi(&["submit"])
+        .assert()
+        .failure()
+        .stderr(contains("Failed to look up pull requests"));
+
+    assert!(sandbox.metadata(&branch).unwrap().get("prNumber").is_none());
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use common::Sandbox;
+use httpmock::{Method, Mock, MockServer};
+use predicates::str::contains;
+use serde_json::{json, Value};
+
+const HOST: &str = "gitlab.example.com";
+const PROJECT: &str = "group/subgroup/repo";
+// Projects in nested groups are addressed by their URL-encoded full path
+const MERGE_REQUESTS_PATH: &str = "/projects/group%2Fsubgroup%2Frepo/merge_requests";
+
+/// A merge request as the GitLab API returns it.
+fn merge_request(iid: u64, branch: &str, base: &str, state: &str, description: &str) -> Value {
+    json!({
+        "iid": iid,
+        "web_url": format!("https://{HOST}/{PROJECT}/-/merge_requests/{iid}"),
+        "source_branch": branch,
+        "target_branch": base,
+        "title": "Add the lexer",
+        "description": description,
+        "state": state,
+    })
+}
+
+/// Mocks the lookup of the open merge requests of a branch, returning the specified ones.
+fn mock_find<'a>(server: &'a MockServer, branch: &str, found: Vec<Value>) -> Mock<'a> {
+    server.mock(|when, then| {
+        when.method(Method::GET)
+            .path(MERGE_REQUESTS_PATH)
+            .query_param("source_branch", branch)
+            .query_param("state", "opened");
+        then.status(200).json_body(Value::Array(found));
+    })
+}
+
+#[test]
+fn submit_opens_a_merge_request() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, PROJECT, "GitLab", &server);
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+
+    let find = mock_find(&server, &branch, vec![]);
+    let create = server.mock(|when, then| {
+        when.method(Method::POST)
+            .path(MERGE_REQUESTS_PATH)
+            .header("PRIVATE-TOKEN", "test-token")
+            .json_body(json!({
+                "source_branch": branch,
+                "target_branch": "main",
+                "title": "Add the lexer",
+                "description": "",
+            }));
+        then.status(201)
+            .json_body(merge_request(1, &branch, "main", "opened", ""));
+    });
+    let update = server.mock(|when, then| {
+        when.method(Method::PUT)
+            .path(format!("{MERGE_REQUESTS_PATH}/1"))
+            .body_contains("gi-stack-start");
+        then.status(200)
+            .json_body(merge_request(1, &branch, "main", "opened", "stack"));
+    });
+
+    sandbox.gi(&["submit"]).assert().success();
+
+    find.assert();
+    create.assert();
+    update.assert();
+    assert_eq!(sandbox.metadata(&branch).unwrap()["prNumber"], 1);
+}
+
+#[test]
+fn submit_updates_the_existing_merge_request() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, PROJECT, "GitLab", &server);
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+
+    mock_find(
+        &server,
+        &branch,
+        vec![merge_request(7, &branch, "develop", "opened", "Tokenizes.")],
+    );
+    let retarget = server.mock(|when, then| {
+        when.method(Method::PUT)
+            .path(format!("{MERGE_REQUESTS_PATH}/7"))
+            .json_body(json!({
+                "target_branch": "main",
+                "title": "Add the lexer",
+                "description": "Tokenizes.",
+            }));
+        then.status(200)
+            .json_body(merge_request(7, &branch, "main", "opened", "Tokenizes."));
+    });
+    server.mock(|when, then| {
+        when.method(Method::PUT)
+            .path(format!("{MERGE_REQUESTS_PATH}/7"))
+            .body_contains("gi-stack-start");
+        then.status(200)
+            .json_body(merge_request(7, &branch, "main", "opened", "stack"));
+    });
+
+    sandbox.gi(&["submit"]).assert().success();
+
+    retarget.assert();
+    assert_eq!(sandbox.metadata(&branch).unwrap()["prNumber"], 7);
+}
+
+#[test]
+fn sync_deletes_branches_of_merged_merge_requests() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, PROJECT, "GitLab", &server);
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let mut metadata = sandbox.metadata(&branch).unwrap();
+    metadata["prNumber"] = json!(3);
+    sandbox.set_metadata(&branch, &metadata);
+
+    let get = server.mock(|when, then| {
+        when.method(Method::GET)
+            .path(format!("{MERGE_REQUESTS_PATH}/3"))
+            .header("PRIVATE-TOKEN", "test-token");
+        then.status(200)
+            .json_body(merge_request(3, &branch, "main", "merged", ""));
+    });
+
+    sandbox.gi(&["sync", "--force"]).assert().success();
+
+    get.assert();
+    assert_eq!(sandbox.branches(), vec!["main".to_string()]);
+    assert!(sandbox.metadata(&branch).is_none());
+}
+
+#[test]
+fn submit_reports_api_errors() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::for_provider(HOST, PROJECT, "GitLab", &server);
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+
+    server.mock(|when, then| {
+        when.method(Method::GET).path(MERGE_REQUESTS_PATH);
+        then.status(401)
+            .json_body(json!({ "message": "401 Unauthorized" }));
+    });
+
+    sandbox
+        .gi(&["submit"])
+        .assert()
+        .failure()
+        .stderr(contains("Failed to look up merge requests"));
+
+    assert!(sandbox.metadata(&branch).unwrap().get("prNumber").is_none());
+}
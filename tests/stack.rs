@@ -0,0 +1,568 @@
+mod common;
+
+use common::Sandbox;
+use predicates::{prelude::*, str::contains};
+use serde_json::{json, Value};
+
+#[test]
+fn create_tracks_the_new_branch_on_top_of_the_current_one() {
+    let sandbox = Sandbox::new();
+
+    let bottom = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let top = sandbox.create_branch("parser.rs", "Add the parser");
+
+    assert!(bottom.ends_with("-Add_the_lexer"), "{bottom}");
+    assert!(top.ends_with("-Add_the_parser"), "{top}");
+    assert_eq!(
+        sandbox.branches(),
+        vec![bottom.clone(), top.clone(), "main".into()]
+    );
+    assert_eq!(
+        sandbox.git(&["log", "-1", "--format=%s", &top]),
+        "Add the parser"
+    );
+
+    let bottom_metadata = sandbox.metadata(&bottom).unwrap();
+    assert_eq!(bottom_metadata["parentBranchName"], "main");
+    assert_eq!(
+        bottom_metadata["parentBranchRevision"],
+        sandbox.revision("main")
+    );
+
+    let top_metadata = sandbox.metadata(&top).unwrap();
+    assert_eq!(top_metadata["parentBranchName"], bottom.as_str());
+    assert_eq!(
+        top_metadata["parentBranchRevision"],
+        sandbox.revision(&bottom)
+    );
+
+    assert!(sandbox.metadata("main").is_none());
+    assert_eq!(sandbox.project_config()["trunk"], "main");
+}
+
+#[test]
+fn create_stages_every_change_only_when_asked() {
+    let sandbox = Sandbox::new();
+    sandbox.write_file("lexer.rs", "Add the lexer");
+
+    // Without a terminal, there's nobody to ask whether to commit the unstaged changes
+    sandbox
+        .create("Add the lexer")
+        .failure()
+        .stderr(contains("--all"));
+
+    assert_eq!(sandbox.branches(), vec!["main"]);
+    assert_eq!(sandbox.current_branch(), "main");
+
+    sandbox
+        .gi(&["create", "--all", "-m", "Add the lexer"])
+        .assert()
+        .success();
+
+    assert!(sandbox.current_branch().ends_with("-Add_the_lexer"));
+    assert_eq!(sandbox.git(&["status", "--porcelain"]), "");
+}
+
+#[test]
+fn create_requires_a_message_when_non_interactive() {
+    let sandbox = Sandbox::new();
+    sandbox.stage_file("lexer.rs", "Add the lexer");
+
+    sandbox
+        .gi(&["create"])
+        .assert()
+        .failure()
+        .stderr(contains("-m"));
+
+    assert_eq!(sandbox.branches(), vec!["main"]);
+    assert_eq!(sandbox.current_branch(), "main");
+}
+
+#[test]
+fn trunk_is_required_when_non_interactive() {
+    let sandbox = Sandbox::new();
+    std::fs::remove_file(sandbox.work_dir().join(".git/.gi_project_config")).unwrap();
+
+    sandbox
+        .gi(&["log"])
+        .assert()
+        .failure()
+        .stderr(contains("--trunk"));
+
+    sandbox.gi(&["--trunk", "main", "log"]).assert().success();
+    assert_eq!(sandbox.project_config()["trunk"], "main");
+}
+
+#[test]
+fn create_without_changes_fails() {
+    let sandbox = Sandbox::new();
+
+    sandbox
+        .create("Add the lexer")
+        .failure()
+        .stderr(contains("without changes in the working area"));
+}
+
+#[test]
+fn log_renders_the_stack_tree() {
+    let sandbox = Sandbox::new();
+    let bottom = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let top = sandbox.create_branch("parser.rs", "Add the parser");
+
+    sandbox
+        .gi(&["log"])
+        .assert()
+        .success()
+        .stdout(format!("main\n└── {bottom}\n    └── {top} (current)\n"));
+}
+
+#[test]
+fn modify_restacks_the_branches_above() {
+    let sandbox = Sandbox::new();
+    let bottom = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let top = sandbox.create_branch("parser.rs", "Add the parser");
+
+    sandbox.git(&["checkout", &bottom]);
+    sandbox.stage_file("lexer.rs", "Add the lexer, faster");
+    sandbox.gi(&["modify"]).assert().success();
+    assert_eq!(
+        sandbox.git(&["log", "-1", "--format=%s", &bottom]),
+        "Add the lexer"
+    );
+
+    let bottom_revision = sandbox.revision(&bottom);
+    assert_eq!(sandbox.revision(&format!("{top}~1")), bottom_revision);
+    assert_eq!(
+        sandbox.metadata(&top).unwrap()["parentBranchRevision"],
+        bottom_revision
+    );
+    assert_eq!(sandbox.current_branch(), bottom);
+    assert_eq!(
+        sandbox.git(&["log", "--format=%s", &top]).lines().count(),
+        3
+    );
+}
+
+#[test]
+fn restack_conflicts_can_be_aborted() {
+    let sandbox = Sandbox::new();
+    let bottom = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let top = sandbox.create_branch("lexer.rs", "Rewrite the lexer");
+    let top_revision = sandbox.revision(&top);
+    let top_metadata = sandbox.metadata(&top).unwrap();
+
+    sandbox.git(&["checkout", &bottom]);
+    sandbox.write_file("lexer.rs", "Fix the lexer");
+    sandbox.git(&["commit", "-am", "Fix the lexer"]);
+
+    sandbox
+        .gi(&["restack"])
+        .assert()
+        .failure()
+        .stderr(contains("gi continue"));
+
+    sandbox.gi(&["abort"]).assert().success();
+
+    assert_eq!(sandbox.revision(&top), top_revision);
+    assert_eq!(sandbox.metadata(&top).unwrap(), top_metadata);
+    assert_eq!(sandbox.current_branch(), bottom);
+    sandbox
+        .gi(&["abort"])
+        .assert()
+        .failure()
+        .stderr(contains("no gi operation"));
+}
+
+#[test]
+fn operations_run_in_linked_worktrees() {
+    let sandbox = Sandbox::new();
+    let bottom = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let top = sandbox.create_branch("lexer.rs", "Rewrite the lexer");
+    let top_revision = sandbox.revision(&top);
+
+    // The `.git` of a linked worktree is a file pointing to its own directory in the main one
+    sandbox.git(&["checkout", "main"]);
+    let worktree = sandbox.work_dir().with_file_name("linked");
+    let worktree_path = worktree.to_str().unwrap();
+    sandbox.git(&["worktree", "add", worktree_path, &bottom]);
+    std::fs::write(worktree.join("lexer.rs"), "Fix the lexer").unwrap();
+    sandbox.git(&["-C", worktree_path, "commit", "-am", "Fix the lexer"]);
+
+    // The trunk is read from the settings shared by every worktree
+    sandbox
+        .gi(&["restack"])
+        .current_dir(&worktree)
+        .assert()
+        .failure()
+        .stderr(contains("gi continue"));
+    assert!(sandbox
+        .work_dir()
+        .join(".git/worktrees/linked/.gi_operation")
+        .exists());
+
+    sandbox.write_user_config(&json!({ "gitBackend": "native" }).to_string());
+    sandbox
+        .gi(&["abort"])
+        .current_dir(&worktree)
+        .assert()
+        .success();
+
+    assert_eq!(sandbox.revision(&top), top_revision);
+    assert_eq!(
+        sandbox.git(&["-C", worktree_path, "branch", "--show-current"]),
+        bottom
+    );
+}
+
+#[test]
+fn aborting_restores_branches_rewritten_by_modify_and_squash() {
+    let sandbox = Sandbox::new();
+    let bottom = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let top = sandbox.create_branch("lexer.rs", "Rewrite the lexer");
+    let top_revision = sandbox.revision(&top);
+    sandbox.git(&["checkout", &bottom]);
+    let bottom_revision = sandbox.revision(&bottom);
+
+    sandbox.stage_file("lexer.rs", "Fix the lexer");
+    sandbox
+        .gi(&["modify"])
+        .assert()
+        .failure()
+        .stderr(contains("gi continue"));
+    sandbox.gi(&["abort"]).assert().success();
+
+    assert_eq!(sandbox.revision(&bottom), bottom_revision);
+    assert_eq!(sandbox.revision(&top), top_revision);
+    assert_eq!(sandbox.current_branch(), bottom);
+
+    // A commit made with git leaves the top branch stacked on an older revision, so it conflicts
+    // when replayed onto the squashed commit
+    sandbox.write_file("lexer.rs", "Fix the lexer");
+    sandbox.git(&["commit", "-am", "Fix the lexer"]);
+    let bottom_revision = sandbox.revision(&bottom);
+    sandbox
+        .gi(&["squash", "-m", "Add the lexer"])
+        .assert()
+        .failure()
+        .stderr(contains("gi continue"));
+    sandbox.gi(&["abort"]).assert().success();
+
+    assert_eq!(sandbox.revision(&bottom), bottom_revision);
+    assert_eq!(sandbox.revision(&top), top_revision);
+    assert_eq!(sandbox.current_branch(), bottom);
+}
+
+#[test]
+fn navigation_moves_through_the_stack() {
+    let sandbox = Sandbox::new();
+    let bottom = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let middle = sandbox.create_branch("parser.rs", "Add the parser");
+    let top = sandbox.create_branch("checker.rs", "Add the checker");
+
+    sandbox.gi(&["bottom"]).assert().success();
+    assert_eq!(sandbox.current_branch(), bottom);
+
+    sandbox.gi(&["up"]).assert().success();
+    assert_eq!(sandbox.current_branch(), middle);
+
+    sandbox.gi(&["top"]).assert().success();
+    assert_eq!(sandbox.current_branch(), top);
+
+    sandbox.gi(&["down", "2"]).assert().success();
+    assert_eq!(sandbox.current_branch(), bottom);
+
+    sandbox
+        .gi(&["down"])
+        .assert()
+        .success()
+        .stdout(contains("Checked out main."));
+    assert_eq!(sandbox.current_branch(), "main");
+}
+
+#[test]
+fn json_output_describes_the_result_of_each_command() {
+    let sandbox = Sandbox::new();
+    sandbox.stage_file("lexer.rs", "Add the lexer");
+
+    let output = sandbox
+        .gi(&["--json", "create", "-m", "Add the lexer"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let created: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let branch = sandbox.current_branch();
+    assert_eq!(created["branch"], branch.as_str());
+    assert_eq!(created["revision"], sandbox.revision(&branch));
+    assert_eq!(created["metadata"], sandbox.metadata(&branch).unwrap());
+
+    let output = sandbox.gi(&["log", "--json"]).output().unwrap();
+    let graph: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        graph,
+        json!({
+            "trunk": "main",
+            "roots": ["main"],
+            "currentBranch": branch,
+            "branches": [{
+                "name": branch,
+                "metadata": sandbox.metadata(&branch).unwrap(),
+                "children": [],
+                "needsRestack": false,
+            }],
+        })
+    );
+
+    let output = sandbox.gi(&["down", "--json"]).output().unwrap();
+    let checked_out: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(checked_out, json!({ "branch": "main" }));
+}
+
+#[test]
+fn json_errors_include_the_suggestion() {
+    let sandbox = Sandbox::new();
+
+    let output = sandbox.gi(&["create", "--json"]).output().unwrap();
+    assert!(!output.status.success());
+
+    let error: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        error["error"]["message"],
+        "Can't create a new stacked commit without changes in the working area."
+    );
+    assert_eq!(
+        error["error"]["suggestion"],
+        "Make some changes to your files and try again."
+    );
+}
+
+#[test]
+fn json_errors_include_the_failures_of_git() {
+    let sandbox = Sandbox::new();
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+    // The recorded revision of the parent no longer exists, e.g. after a garbage collection
+    let mut metadata = sandbox.metadata(&branch).unwrap();
+    metadata["parentBranchRevision"] = json!("1111111111111111111111111111111111111111");
+    sandbox.set_metadata(&branch, &metadata);
+
+    let output = sandbox.gi(&["restack", "--json"]).output().unwrap();
+    assert!(!output.status.success());
+
+    let error: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let message = error["error"]["message"].as_str().unwrap();
+    assert!(
+        message.starts_with("Failed to check revision ancestry: fatal:"),
+        "{message}"
+    );
+    assert!(
+        output.stderr.is_empty(),
+        "{:?}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn branches_made_with_git_can_be_tracked_and_untracked() {
+    let sandbox = Sandbox::new();
+    let bottom = sandbox.create_branch("lexer.rs", "Add the lexer");
+    sandbox.git(&["checkout", "-b", "parser"]);
+    sandbox.stage_file("parser.rs", "Add the parser");
+    sandbox.git(&["commit", "-m", "Add the parser"]);
+
+    // Without a terminal, the parent can't be confirmed
+    sandbox
+        .gi(&["track"])
+        .assert()
+        .failure()
+        .stderr(contains(format!("--parent {bottom}")));
+
+    sandbox
+        .gi(&["track", "parser", "--parent", &bottom])
+        .assert()
+        .success();
+    assert_eq!(
+        sandbox.metadata("parser").unwrap(),
+        json!({
+            "parentBranchName": bottom,
+            "parentBranchRevision": sandbox.revision(&bottom),
+        })
+    );
+    sandbox
+        .gi(&["track", "parser", "--parent", "main"])
+        .assert()
+        .failure()
+        .stderr(contains("gi untrack parser"));
+
+    sandbox.gi(&["untrack"]).assert().success();
+    assert!(sandbox.metadata("parser").is_none());
+    assert_eq!(sandbox.current_branch(), "parser");
+    assert_eq!(
+        sandbox.branches(),
+        vec![bottom, "main".into(), "parser".into()]
+    );
+}
+
+#[test]
+fn move_rebases_the_branch_onto_its_new_parent() {
+    let sandbox = Sandbox::new();
+    let lexer = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let parser = sandbox.create_branch("parser.rs", "Add the parser");
+    let checker = sandbox.create_branch("checker.rs", "Add the checker");
+
+    // The branches stacked on top stay where they are
+    sandbox.git(&["checkout", &parser]);
+    sandbox.gi(&["move", "--onto", "main"]).assert().success();
+
+    assert_eq!(sandbox.current_branch(), parser);
+    assert_eq!(
+        sandbox.git(&["log", "--format=%s", &parser]),
+        "Add the parser\nInitial commit"
+    );
+    assert_eq!(
+        sandbox.metadata(&parser).unwrap()["parentBranchName"],
+        "main"
+    );
+    assert_eq!(
+        sandbox.git(&["log", "--format=%s", &checker]),
+        "Add the checker\nAdd the lexer\nInitial commit"
+    );
+    assert_eq!(
+        sandbox.metadata(&checker).unwrap()["parentBranchName"],
+        lexer.as_str()
+    );
+
+    // The descendants move along when asked
+    sandbox.git(&["checkout", &lexer]);
+    sandbox
+        .gi(&["move", "--onto", &parser, "--descendants"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        sandbox.git(&["log", "--format=%s", &checker]),
+        "Add the checker\nAdd the lexer\nAdd the parser\nInitial commit"
+    );
+    assert_eq!(
+        sandbox.metadata(&lexer).unwrap()["parentBranchRevision"],
+        sandbox.revision(&parser)
+    );
+    sandbox
+        .gi(&["move", "--onto", &checker, "--descendants"])
+        .assert()
+        .failure()
+        .stderr(contains("moves along with it"));
+}
+
+#[test]
+fn split_by_commit_stacks_every_commit_on_its_own_branch() {
+    let sandbox = Sandbox::new();
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+    sandbox.stage_file("parser.rs", "Add the parser");
+    sandbox.git(&["commit", "-m", "Add the parser"]);
+    let revision = sandbox.revision(&branch);
+
+    sandbox
+        .gi(&["split"])
+        .assert()
+        .failure()
+        .stderr(contains("--by commit"));
+    sandbox.gi(&["split", "--by", "commit"]).assert().success();
+
+    let lexer = sandbox.metadata(&branch).unwrap()["parentBranchName"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert!(lexer.ends_with("-Add_the_lexer1"), "{lexer}");
+    assert_eq!(
+        sandbox.metadata(&lexer).unwrap()["parentBranchName"],
+        "main"
+    );
+    assert_eq!(
+        sandbox.git(&["log", "--format=%s", &lexer]),
+        "Add the lexer\nInitial commit"
+    );
+    assert_eq!(sandbox.revision(&branch), revision);
+    assert_eq!(sandbox.current_branch(), branch);
+    assert!(sandbox.git(&["status", "--porcelain"]).is_empty());
+}
+
+#[test]
+fn squash_and_fold_collapse_the_stack() {
+    let sandbox = Sandbox::new();
+    let lexer = sandbox.create_branch("lexer.rs", "Add the lexer");
+    sandbox.stage_file("lexer.rs", "Add the lexer, fixed");
+    sandbox.git(&["commit", "-m", "Fix the lexer"]);
+    let parser = sandbox.create_branch("parser.rs", "Add the parser");
+    let checker = sandbox.create_branch("checker.rs", "Add the checker");
+
+    sandbox.git(&["checkout", &lexer]);
+    sandbox
+        .gi(&["squash"])
+        .assert()
+        .failure()
+        .stderr(contains("-m"));
+    sandbox
+        .gi(&["squash", "-m", "Add the lexer"])
+        .assert()
+        .success();
+    assert_eq!(
+        sandbox.git(&["log", "--format=%s", &parser]),
+        "Add the parser\nAdd the lexer\nInitial commit"
+    );
+    assert!(sandbox.git(&["status", "--porcelain"]).is_empty());
+
+    sandbox.git(&["checkout", &parser]);
+    let parser_revision = sandbox.revision(&parser);
+    sandbox.gi(&["fold"]).assert().success();
+
+    assert_eq!(sandbox.current_branch(), lexer);
+    assert_eq!(sandbox.revision(&lexer), parser_revision);
+    assert_eq!(
+        sandbox.branches(),
+        vec![checker.clone(), lexer.clone(), "main".into()]
+    );
+    assert!(sandbox.metadata(&parser).is_none());
+    assert_eq!(
+        sandbox.metadata(&checker).unwrap()["parentBranchName"],
+        lexer.as_str()
+    );
+    assert!(sandbox.git(&["status", "--porcelain"]).is_empty());
+}
+
+#[test]
+fn the_native_backend_restacks_and_falls_back_outside_of_repositories() {
+    let sandbox = Sandbox::new();
+    sandbox.write_user_config(&json!({ "gitBackend": "native" }).to_string());
+    let bottom = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let top = sandbox.create_branch("parser.rs", "Add the parser");
+
+    sandbox.git(&["checkout", &bottom]);
+    sandbox.stage_file("lexer.rs", "Add the lexer, faster");
+    sandbox.gi(&["modify"]).assert().success();
+    assert_eq!(
+        sandbox.revision(&format!("{top}~1")),
+        sandbox.revision(&bottom)
+    );
+
+    // Without a repository to open, the error is reported with the usual suggestion
+    let outside = tempfile::tempdir().unwrap();
+    sandbox
+        .gi(&["log"])
+        .current_dir(outside.path())
+        .assert()
+        .failure()
+        .stderr(contains("not inside a git repository").and(contains("panicked").not()));
+}
+
+#[test]
+fn malformed_user_settings_are_reported_instead_of_panicking() {
+    let sandbox = Sandbox::new();
+    sandbox.write_user_config("{ not json");
+
+    sandbox
+        .gi(&["log"])
+        .assert()
+        .failure()
+        .stderr(contains("Failed to parse user settings"))
+        .stderr(contains("Fix or remove ~/.config/gi/config"))
+        .stderr(contains("panicked").not());
+}
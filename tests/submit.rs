@@ -0,0 +1,306 @@
+mod common;
+
+use common::{Sandbox, OWNER, REPO};
+use httpmock::{Method, Mock, MockServer};
+use predicates::str::contains;
+use serde_json::{json, Value};
+
+const PULLS_PATH: &str = "/repos/owner/repo/pulls";
+
+/// A pull request as the GitHub API returns it.
+fn pull_request(number: u64, branch: &str, base: &str, title: &str, body: &str) -> Value {
+    json!({
+        "url": format!("https://api.github.com/repos/{OWNER}/{REPO}/pulls/{number}"),
+        "id": 1000 + number,
+        "number": number,
+        "html_url": format!("https://github.com/{OWNER}/{REPO}/pull/{number}"),
+        "title": title,
+        "body": body,
+        "head": { "ref": branch, "sha": "0000000000000000000000000000000000000000" },
+        "base": { "ref": base, "sha": "0000000000000000000000000000000000000000" },
+    })
+}
+
+/// Mocks the lookup of the open pull requests of a branch, returning the specified ones.
+fn mock_find<'a>(server: &'a MockServer, branch: &str, found: Vec<Value>) -> Mock<'a> {
+    server.mock(|when, then| {
+        when.method(Method::GET)
+            .path(PULLS_PATH)
+            .query_param("head", format!("{OWNER}:{branch}"))
+            .query_param("state", "open");
+        then.status(200).json_body(Value::Array(found));
+    })
+}
+
+#[test]
+fn submit_pushes_the_branch_and_opens_a_pull_request() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::new();
+    sandbox.use_github_api(&server.base_url());
+
+    sandbox.stage_file("lexer.rs", "lexer");
+    sandbox.create("Add the lexer").success();
+    let branch = sandbox.current_branch();
+
+    let find = mock_find(&server, &branch, vec![]);
+    let create = server.mock(|when, then| {
+        when.method(Method::POST)
+            .path(PULLS_PATH)
+            .header("authorization", "Bearer test-token")
+            .json_body(json!({
+                "title": "Add the lexer",
+                "head": format!("{OWNER}:{branch}"),
+                "base": "main",
+                "body": "",
+            }));
+        then.status(201)
+            .json_body(pull_request(1, &branch, "main", "Add the lexer", ""));
+    });
+    // The stack section is added to the description once every pull request is known
+    let update = server.mock(|when, then| {
+        when.method(Method::PATCH)
+            .path(format!("{PULLS_PATH}/1"))
+            .body_contains("gi-stack-start");
+        then.status(200)
+            .json_body(pull_request(1, &branch, "main", "Add the lexer", "stack"));
+    });
+
+    let output = sandbox.gi(&["submit", "--json"]).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        serde_json::from_slice::<Value>(&output.stdout).unwrap(),
+        json!({
+            "pullRequests": [{
+                "branch": branch,
+                "base": "main",
+                "number": 1,
+                "url": "https://github.com/owner/repo/pull/1",
+                "created": true,
+            }]
+        })
+    );
+
+    find.assert();
+    create.assert();
+    update.assert();
+
+    assert_eq!(
+        sandbox.remote_git(&["rev-parse", &branch]),
+        sandbox.revision(&branch)
+    );
+    assert_eq!(sandbox.metadata(&branch).unwrap()["prNumber"], 1);
+    assert_eq!(sandbox.project_config()["pushRemote"], Value::Null);
+}
+
+#[test]
+fn submit_stack_opens_pull_requests_against_parents() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::new();
+    sandbox.use_github_api(&server.base_url());
+
+    let bottom = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let top = sandbox.create_branch("parser.rs", "Add the parser");
+
+    mock_find(&server, &bottom, vec![]);
+    mock_find(&server, &top, vec![]);
+    let create_bottom = server.mock(|when, then| {
+        when.method(Method::POST)
+            .path(PULLS_PATH)
+            .json_body_partial(json!({ "base": "main" }).to_string());
+        then.status(201)
+            .json_body(pull_request(1, &bottom, "main", "Add the lexer", ""));
+    });
+    let create_top = server.mock(|when, then| {
+        when.method(Method::POST)
+            .path(PULLS_PATH)
+            .json_body_partial(json!({ "base": bottom }).to_string());
+        then.status(201)
+            .json_body(pull_request(2, &top, &bottom, "Add the parser", ""));
+    });
+    let update_bottom = server.mock(|when, then| {
+        when.method(Method::PATCH)
+            .path(format!("{PULLS_PATH}/1"))
+            .body_contains("#2");
+        then.status(200)
+            .json_body(pull_request(1, &bottom, "main", "Add the lexer", ""));
+    });
+    let update_top = server.mock(|when, then| {
+        when.method(Method::PATCH)
+            .path(format!("{PULLS_PATH}/2"))
+            .body_contains("#1");
+        then.status(200)
+            .json_body(pull_request(2, &top, &bottom, "Add the parser", ""));
+    });
+
+    sandbox.gi(&["submit", "--stack"]).assert().success();
+
+    create_bottom.assert();
+    create_top.assert();
+    update_bottom.assert();
+    update_top.assert();
+
+    assert_eq!(sandbox.metadata(&bottom).unwrap()["prNumber"], 1);
+    assert_eq!(sandbox.metadata(&top).unwrap()["prNumber"], 2);
+    for branch in [&bottom, &top] {
+        assert_eq!(
+            sandbox.remote_git(&["rev-parse", branch]),
+            sandbox.revision(branch)
+        );
+    }
+}
+
+#[test]
+fn submit_takes_the_title_and_description_from_the_command_line() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::new();
+    sandbox.use_github_api(&server.base_url());
+
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+    sandbox.write_file("description.md", "Tokenizes the input.");
+
+    mock_find(&server, &branch, vec![]);
+    let create = server.mock(|when, then| {
+        when.method(Method::POST)
+            .path(PULLS_PATH)
+            .json_body_partial(
+                json!({ "title": "Lexer", "body": "Tokenizes the input." }).to_string(),
+            );
+        then.status(201)
+            .json_body(pull_request(1, &branch, "main", "Lexer", ""));
+    });
+    server.mock(|when, then| {
+        when.method(Method::PATCH).path(format!("{PULLS_PATH}/1"));
+        then.status(200)
+            .json_body(pull_request(1, &branch, "main", "Lexer", ""));
+    });
+
+    sandbox
+        .gi(&[
+            "submit",
+            "--title",
+            "Lexer",
+            "--body-file",
+            "description.md",
+            "--no-interactive",
+        ])
+        .assert()
+        .success();
+
+    create.assert();
+}
+
+#[test]
+fn submit_requires_a_token_when_non_interactive() {
+    let sandbox = Sandbox::new();
+    sandbox.create_branch("lexer.rs", "Add the lexer");
+
+    sandbox
+        .gi(&["submit"])
+        .assert()
+        .failure()
+        .stderr(contains("access token"));
+}
+
+#[test]
+fn submit_reports_api_errors() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::new();
+    sandbox.use_github_api(&server.base_url());
+
+    let branch = sandbox.create_branch("lexer.rs", "Add the lexer");
+    server.mock(|when, then| {
+        when.method(Method::GET).path(PULLS_PATH);
+        then.status(401)
+            .json_body(json!({ "message": "Bad credentials" }));
+    });
+
+    sandbox
+        .gi(&["submit"])
+        .assert()
+        .failure()
+        .stderr(contains("Failed to look up pull requests"));
+
+    assert!(sandbox.metadata(&branch).unwrap().get("prNumber").is_none());
+}
+
+#[test]
+fn sync_prunes_merged_branches_and_restacks_the_rest() {
+    let server = MockServer::start();
+    let sandbox = Sandbox::new();
+    sandbox.use_github_api(&server.base_url());
+
+    let bottom = sandbox.create_branch("lexer.rs", "Add the lexer");
+    let top = sandbox.create_branch("parser.rs", "Add the parser");
+    for (branch, number) in [(&bottom, 1), (&top, 2)] {
+        let mut metadata = sandbox.metadata(branch).unwrap();
+        metadata["prNumber"] = json!(number);
+        sandbox.set_metadata(branch, &metadata);
+    }
+
+    // The bottom pull request was squash merged on the remote
+    sandbox.git(&["checkout", "main"]);
+    sandbox.git(&["merge", "--squash", &bottom]);
+    sandbox.git(&["commit", "-m", "Add the lexer (#1)"]);
+    sandbox.git(&["push", "origin", "main"]);
+    sandbox.git(&["reset", "--hard", "HEAD~1"]);
+    sandbox.git(&["checkout", &top]);
+
+    let mut merged = pull_request(1, &bottom, "main", "Add the lexer", "");
+    merged["state"] = json!("closed");
+    merged["merged_at"] = json!("2024-01-01T00:00:00Z");
+    server.mock(|when, then| {
+        when.method(Method::GET).path(format!("{PULLS_PATH}/1"));
+        then.status(200).json_body(merged);
+    });
+    let mut open = pull_request(2, &top, &bottom, "Add the parser", "");
+    open["state"] = json!("open");
+    server.mock(|when, then| {
+        when.method(Method::GET).path(format!("{PULLS_PATH}/2"));
+        then.status(200).json_body(open);
+    });
+
+    sandbox
+        .gi(&["sync"])
+        .assert()
+        .failure()
+        .stderr(contains("--force"));
+    sandbox.gi(&["sync", "--force"]).assert().success();
+
+    assert_eq!(
+        sandbox.revision("main"),
+        sandbox.remote_git(&["rev-parse", "main"])
+    );
+    assert_eq!(sandbox.branches(), vec![top.clone(), "main".into()]);
+    assert!(sandbox.metadata(&bottom).is_none());
+
+    let metadata = sandbox.metadata(&top).unwrap();
+    assert_eq!(metadata["parentBranchName"], "main");
+    assert_eq!(metadata["parentBranchRevision"], sandbox.revision("main"));
+    assert_eq!(
+        sandbox.revision(&format!("{top}~1")),
+        sandbox.revision("main")
+    );
+    assert_eq!(sandbox.current_branch(), top);
+}
+
+#[test]
+fn host_maps_a_host_to_a_provider() {
+    let sandbox = Sandbox::new();
+
+    sandbox
+        .gi(&[
+            "host",
+            "git.example.com",
+            "--provider",
+            "gitlab",
+            "--api-url",
+            "https://git.example.com/api/v4",
+        ])
+        .assert()
+        .success();
+
+    assert_eq!(
+        sandbox.user_config()["hosts"]["git.example.com"],
+        json!({ "provider": "GitLab", "apiUrl": "https://git.example.com/api/v4" })
+    );
+}